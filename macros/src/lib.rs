@@ -0,0 +1,179 @@
+extern crate lambda;
+extern crate proc_macro;
+
+use std::str::FromStr;
+use lambda::AstNode;
+use lambda::parser;
+use proc_macro::{Delimiter, Group, Ident, Literal, Punct, Spacing, Span, TokenStream, TokenTree};
+
+/// Parses a lambda term at compile time and expands to an expression
+/// building its `AstNode`, so `lambda!(r"\xy.x")` is the same as
+/// `parser::parse_object(r"\xy.x")` without the runtime parsing.
+/// Syntax errors are reported as compile errors:
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate lambda_macros;
+/// extern crate lambda;
+///
+/// fn main() {
+///     let _ = lambda!(r"\x.");
+/// }
+/// ```
+///
+/// ```compile_fail
+/// #[macro_use]
+/// extern crate lambda_macros;
+/// extern crate lambda;
+///
+/// fn main() {
+///     let _ = lambda!(x);
+/// }
+/// ```
+#[proc_macro]
+pub fn lambda(input: TokenStream) -> TokenStream {
+	let mut tokens = input.into_iter();
+	let literal = match (tokens.next(), tokens.next()) {
+		(Some(TokenTree::Literal(literal)), None) => literal,
+		(Some(token), _) => return compile_error("expected a string literal", token.span()),
+		(None, _) => return compile_error("expected a string literal", Span::call_site()),
+	};
+
+	let source = match string_value(&literal.to_string()) {
+		Some(source) => source,
+		None => return compile_error("expected a string literal", literal.span()),
+	};
+
+	match expand(&source) {
+		Ok(code) => TokenStream::from_str(&code).unwrap(),
+		Err(message) => compile_error(&message, literal.span()),
+	}
+}
+
+/// Code of the expression building the term, or the syntax error.
+fn expand(source: &str) -> Result<String, String> {
+	match parser::parse_object(source) {
+		Ok(node) => {
+			let mut code = String::new();
+			generate(&node, &mut code);
+			Ok(code)
+		},
+		// + 1 because editors index columns starting from 1
+		Err(e) => Err(format!("{} (column {})", e.message, e.position + 1)),
+	}
+}
+
+fn compile_error(message: &str, span: Span) -> TokenStream {
+	let mut message = Literal::string(message);
+	message.set_span(span);
+	let tokens: Vec<TokenTree> = vec![
+		TokenTree::Ident(Ident::new("compile_error", span)),
+		TokenTree::Punct(Punct::new('!', Spacing::Alone)),
+		TokenTree::Group(Group::new(
+			Delimiter::Parenthesis,
+			TokenStream::from(TokenTree::Literal(message)))),
+	];
+	tokens.into_iter().collect()
+}
+
+/// Returns the contents of a string literal as written in source,
+/// handling escapes and raw strings.
+fn string_value(literal: &str) -> Option<String> {
+	if let Some(raw) = literal.strip_prefix('r') {
+		let hashes = raw.chars().take_while(|&ch| ch == '#').count();
+		let start = 2 + hashes;
+		let end = literal.len().checked_sub(1 + hashes);
+		return match end {
+			Some(end) if end >= start => Some(literal[start..end].to_string()),
+			_ => None,
+		};
+	}
+
+	if literal.len() < 2 || !literal.starts_with('"') || !literal.ends_with('"') {
+		return None;
+	}
+
+	let mut result = String::new();
+	let mut chars = literal[1..literal.len() - 1].chars();
+	while let Some(ch) = chars.next() {
+		if ch != '\\' {
+			result.push(ch);
+			continue;
+		}
+		match chars.next() {
+			Some('\\') => result.push('\\'),
+			Some('"') => result.push('"'),
+			Some('\'') => result.push('\''),
+			Some('n') => result.push('\n'),
+			Some('t') => result.push('\t'),
+			Some('r') => result.push('\r'),
+			Some('0') => result.push('\0'),
+			// line continuation
+			Some('\n') => {
+				while chars.as_str().starts_with(|ch: char| ch.is_whitespace()) {
+					chars.next();
+				}
+			},
+			_ => return None,
+		}
+	}
+	Some(result)
+}
+
+fn generate(node: &AstNode, code: &mut String) {
+	match node {
+		&AstNode::FreeVariable(ch) =>
+			code.push_str(&format!("::lambda::AstNode::FreeVariable({:?})", ch)),
+		&AstNode::BoundVariable(num) =>
+			code.push_str(&format!("::lambda::AstNode::BoundVariable({})", num)),
+		AstNode::Application(a, b) => {
+			code.push_str("::lambda::AstNode::Application(::std::boxed::Box::new(");
+			generate(a, code);
+			code.push_str("), ::std::boxed::Box::new(");
+			generate(b, code);
+			code.push_str("))");
+		},
+		AstNode::Function(body) => {
+			code.push_str("::lambda::AstNode::Function(::std::boxed::Box::new(");
+			generate(body, code);
+			code.push_str("))");
+		},
+		AstNode::Name(name) =>
+			code.push_str(&format!("::lambda::AstNode::Name({:?}.to_string())", name)),
+		&AstNode::Integer(num) =>
+			code.push_str(&format!("::lambda::AstNode::Integer({})", num)),
+		&AstNode::Builtin(..) | &AstNode::Foreign(..) =>
+			panic!("native value in parsed term"),
+		&AstNode::Origin(..) =>
+			panic!("origin node in parsed term"),
+		// spans point into the macro input, which is gone at runtime
+		AstNode::Spanned(_, node) => generate(node, code),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn string_literals() {
+		assert_eq!(string_value(r#""\\x.x""#), Some(r"\x.x".to_string()));
+		assert_eq!(string_value(r#"r"\x.x""#), Some(r"\x.x".to_string()));
+		assert_eq!(string_value(r###"r#"\x."x""#"###), Some(r#"\x."x""#.to_string()));
+		assert_eq!(string_value("12"), None);
+	}
+
+	#[test]
+	fn terms_expand_to_constructors() {
+		assert_eq!(expand(r"\x.x y").unwrap(),
+			"::lambda::AstNode::Function(::std::boxed::Box::new(::lambda::AstNode::Application(\
+			::std::boxed::Box::new(::lambda::AstNode::BoundVariable(0)), \
+			::std::boxed::Box::new(::lambda::AstNode::FreeVariable('y')))))");
+	}
+
+	#[test]
+	fn syntax_errors_have_columns() {
+		let message = expand(r"\x.").err().unwrap();
+		assert!(message.ends_with("(column 4)"), "{}", message);
+	}
+}
@@ -0,0 +1,26 @@
+#[macro_use]
+extern crate lambda_macros;
+extern crate lambda;
+
+use lambda::runtime::{numeric_value, Interpreter};
+
+fn interpreter() -> Interpreter {
+	let mut interpreter = Interpreter::new();
+	interpreter.set_quiet(true);
+	interpreter.load_prelude();
+	interpreter
+}
+
+#[test]
+fn terms_are_built_at_compile_time() {
+	let two = lambda!(r"\f.\x.f (f x)");
+	let result = interpreter().evaluate(&two).unwrap();
+	assert_eq!(numeric_value(&result), Some(2));
+}
+
+#[test]
+fn names_are_resolved_by_the_interpreter() {
+	let sum = lambda!("ADD 2 (MUL 3 4)");
+	let result = interpreter().evaluate(&sum).unwrap();
+	assert_eq!(numeric_value(&result), Some(14));
+}
@@ -0,0 +1,348 @@
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use AstNode;
+use church;
+use parser::ParseError;
+use reduction;
+use runtime::boolean_value;
+
+/// Appends the Binary Lambda Calculus encoding of the term:
+/// `00 M` for abstraction, `01 M N` for application and
+/// `1^(n+1) 0` for the variable with de Bruijn index n.
+pub fn encode(node: &AstNode, bits: &mut Vec<bool>) -> Result<(), String> {
+	match node {
+		AstNode::Function(body) => {
+			bits.push(false);
+			bits.push(false);
+			encode(body, bits)
+		},
+		AstNode::Application(a, b) => {
+			bits.push(false);
+			bits.push(true);
+			encode(a, bits)?;
+			encode(b, bits)
+		},
+		&AstNode::BoundVariable(num) => {
+			for _ in 0..(num + 1) {
+				bits.push(true);
+			}
+			bits.push(false);
+			Ok(())
+		},
+		&AstNode::FreeVariable(ch) =>
+			Err(format!("free variable {} can't be encoded", ch)),
+		AstNode::Spanned(_, node) => encode(node, bits),
+		AstNode::Name(name) =>
+			Err(format!("name {} can't be encoded", name)),
+		AstNode::Origin(name, _) =>
+			Err(format!("function tagged with origin {} can't be encoded", name)),
+		&AstNode::Integer(num) =>
+			Err(format!("native integer {} can't be encoded", num)),
+		&AstNode::Builtin(builtin) =>
+			Err(format!("native function {} can't be encoded", builtin.symbol())),
+		AstNode::Foreign(primitive) =>
+			Err(format!("foreign function {} can't be encoded", primitive.name)),
+	}
+}
+
+pub fn to_bit_string(bits: &[bool]) -> String {
+	bits.iter().map(|&bit| if bit { '1' } else { '0' }).collect()
+}
+
+/// Packs bits into bytes, most significant bit first, padding
+/// the last byte with zeros.
+pub fn to_bytes(bits: &[bool]) -> Vec<u8> {
+	bits.chunks(8)
+		.map(|chunk| {
+			let mut byte = 0u8;
+			for i in 0..8 {
+				byte <<= 1;
+				if chunk.get(i) == Some(&true) {
+					byte |= 1;
+				}
+			}
+			byte
+		})
+		.collect()
+}
+
+pub fn bits_from_bytes(bytes: &[u8]) -> Vec<bool> {
+	let mut bits = Vec::new();
+	for &byte in bytes {
+		for i in (0..8).rev() {
+			bits.push(byte & (1 << i) != 0);
+		}
+	}
+	bits
+}
+
+fn decode_walk(bits: &[bool], index: &mut usize, depth: u32) -> Result<AstNode, String> {
+	let start = *index;
+	let mut next_bit = || {
+		let bit = bits.get(*index).cloned();
+		*index += 1;
+		bit.ok_or_else(|| "unexpected end of program".to_string())
+	};
+
+	if next_bit()? {
+		let mut num = 0;
+		while next_bit()? {
+			num += 1;
+		}
+		if num >= depth {
+			return Err(format!("unbound variable at bit {}", start));
+		}
+		Ok(AstNode::BoundVariable(num))
+	} else if next_bit()? {
+		let a = decode_walk(bits, index, depth)?;
+		let b = decode_walk(bits, index, depth)?;
+		Ok(AstNode::Application(Box::new(a), Box::new(b)))
+	} else {
+		let body = decode_walk(bits, index, depth + 1)?;
+		Ok(AstNode::Function(Box::new(body)))
+	}
+}
+
+/// Decodes one closed term from the beginning of the bits, returning
+/// it together with the number of bits it took.
+pub fn decode(bits: &[bool]) -> Result<(AstNode, usize), String> {
+	let mut index = 0;
+	let node = decode_walk(bits, &mut index, 0)?;
+	Ok((node, index))
+}
+
+/// Parses a program written as text of `0` and `1` characters,
+/// ignoring whitespace.
+pub fn parse_bits(source: &str) -> Result<AstNode, ParseError> {
+	let mut bits = Vec::new();
+	let mut positions = Vec::new();
+	for (position, ch) in source.chars().enumerate() {
+		match ch {
+			'0' | '1' => {
+				bits.push(ch == '1');
+				positions.push(position);
+			},
+			' ' | '\t' | '\r' | '\n' => (),
+			_ => return Err(ParseError::new(position, "expected 0 or 1".to_string())),
+		}
+	}
+
+	match decode(&bits) {
+		Ok((node, length)) if length == bits.len() => Ok(node),
+		Ok((_, length)) => Err(ParseError::new(positions[length], "expected end of input".to_string())),
+		Err(message) => Err(ParseError::new(source.chars().count(), message)),
+	}
+}
+
+fn boolean(value: bool) -> AstNode {
+	let var = if value { 1 } else { 0 };
+	AstNode::Function(Box::new(AstNode::Function(
+		Box::new(AstNode::BoundVariable(var)))))
+}
+
+/// Builds `\z.z head tail`, both parts must be closed terms.
+fn cons(head: AstNode, tail: AstNode) -> AstNode {
+	AstNode::Function(Box::new(AstNode::Application(
+		Box::new(AstNode::Application(
+			Box::new(AstNode::BoundVariable(0)),
+			Box::new(head))),
+		Box::new(tail))))
+}
+
+/// Encodes bits as a list of booleans, where 0 is `TRUE` and
+/// 1 is `FALSE`, and the list is terminated by `FALSE`.
+pub fn bit_list(bits: &[bool]) -> AstNode {
+	let mut list = boolean(false);
+	for &bit in bits.iter().rev() {
+		list = cons(boolean(!bit), list);
+	}
+	list
+}
+
+/// Encodes bytes as a list of 8-bit lists, as used by BLC8.
+pub fn byte_list(bytes: &[u8]) -> AstNode {
+	let mut list = boolean(false);
+	for &byte in bytes.iter().rev() {
+		list = cons(bit_list(&bits_from_bytes(&[byte])), list);
+	}
+	list
+}
+
+fn list_items(node: &AstNode) -> Result<Vec<&AstNode>, String> {
+	let mut items = Vec::new();
+	let mut current = node;
+	loop {
+		if boolean_value(current) == Some(false) {
+			return Ok(items);
+		}
+
+		let (head, tail) = match church::split_pair(current) {
+			Some(parts) => parts,
+			None => return Err("output is not a list".to_string()),
+		};
+		items.push(head);
+		current = tail;
+	}
+}
+
+fn decode_bit(node: &AstNode) -> Result<bool, String> {
+	match boolean_value(node) {
+		Some(value) => Ok(!value),
+		None => Err("output list element is not a bit".to_string()),
+	}
+}
+
+pub fn decode_bit_list(node: &AstNode) -> Result<Vec<bool>, String> {
+	let mut bits = Vec::new();
+	for item in list_items(node)? {
+		bits.push(decode_bit(item)?);
+	}
+	Ok(bits)
+}
+
+pub fn decode_byte_list(node: &AstNode) -> Result<Vec<u8>, String> {
+	let mut bytes = Vec::new();
+	for item in list_items(node)? {
+		let bits = decode_bit_list(item)?;
+		if bits.len() != 8 {
+			return Err("output list element is not a byte".to_string());
+		}
+		bytes.push(to_bytes(&bits)[0]);
+	}
+	Ok(bytes)
+}
+
+fn read_program(path: &str, bytes: bool) -> Result<Vec<bool>, String> {
+	let mut contents = Vec::new();
+	if let Err(e) = File::open(path).and_then(|mut f| f.read_to_end(&mut contents)) {
+		return Err(format!("failed to read {}: {}", path, e));
+	}
+
+	if bytes {
+		Ok(bits_from_bytes(&contents))
+	} else {
+		Ok(text_bits(&contents))
+	}
+}
+
+/// Reads the bits written as `0` and `1` characters,
+/// everything else is ignored.
+fn text_bits(contents: &[u8]) -> Vec<bool> {
+	contents.iter()
+		.filter(|&&ch| ch == b'0' || ch == b'1')
+		.map(|&ch| ch == b'1')
+		.collect()
+}
+
+fn run_program(path: &str, bytes: bool) -> Result<(), String> {
+	let program_bits = read_program(path, bytes)?;
+	let (program, length) = decode(&program_bits)?;
+
+	let mut stdin = Vec::new();
+	if let Err(e) = io::stdin().read_to_end(&mut stdin) {
+		return Err(format!("failed to read input: {}", e));
+	}
+
+	// like in the reference implementation, whatever follows the
+	// program in its file is the beginning of the input
+	let input = if bytes {
+		// skipping the rest of the byte the program ends in
+		let first_byte = length.div_ceil(8);
+		let mut input_bytes = to_bytes(&program_bits[first_byte * 8..]);
+		input_bytes.extend(stdin);
+		byte_list(&input_bytes)
+	} else {
+		let mut input_bits = program_bits[length..].to_vec();
+		input_bits.extend(text_bits(&stdin));
+		bit_list(&input_bits)
+	};
+
+	let result = reduction::beta_reduce(
+		&AstNode::Application(Box::new(program), Box::new(input)))?;
+
+	let mut stdout = io::stdout();
+	let written = if bytes {
+		let output = decode_byte_list(&result)?;
+		stdout.write_all(&output)
+	} else {
+		let output = decode_bit_list(&result)?;
+		stdout.write_all(to_bit_string(&output).as_bytes())
+	};
+	written.and_then(|_| stdout.flush())
+		.map_err(|e| format!("failed to write output: {}", e))
+}
+
+/// Runs a BLC program with stdin as its input. In bit mode both the
+/// program and the input are text of `0` and `1` characters, in byte
+/// mode (BLC8) they are raw bytes.
+pub fn run_file(path: &str, bytes: bool) -> bool {
+	match run_program(path, bytes) {
+		Ok(()) => true,
+		Err(message) => {
+			println!("[Error] {}", message);
+			false
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use parser::{self, Syntax};
+
+	fn term(source: &str) -> AstNode {
+		parser::parse(source, Syntax::Lambda).ok().unwrap()
+	}
+
+	fn bits(text: &str) -> Vec<bool> {
+		text_bits(text.as_bytes())
+	}
+
+	#[test]
+	fn terms_survive_encoding() {
+		let node = term("\\f.\\x.f (f x)");
+		let mut encoded = Vec::new();
+		encode(&node, &mut encoded).unwrap();
+		assert_eq!(to_bit_string(&encoded), "0000011100111010");
+		let (decoded, length) = decode(&encoded).unwrap();
+		assert_eq!(length, encoded.len());
+		assert!(reduction::alpha_equivalent(&decoded, &node));
+		assert!(reduction::alpha_equivalent(&parse_bits("0000 0111 0011 1010").ok().unwrap(), &node));
+	}
+
+	#[test]
+	fn only_closed_lambda_terms_are_encoded() {
+		assert!(encode(&term("\\x.y"), &mut Vec::new()).is_err());
+		assert!(encode(&AstNode::Integer(3), &mut Vec::new()).is_err());
+		assert!(encode(&AstNode::Name("ADD".to_string()), &mut Vec::new()).is_err());
+	}
+
+	#[test]
+	fn malformed_programs_are_rejected() {
+		// \x.x followed by an extra bit
+		let error = parse_bits("0010 1").err().unwrap();
+		assert_eq!(error.message, "expected end of input");
+		assert_eq!(error.position, 5);
+		// \x.y, where y is not bound
+		assert!(parse_bits("00110").is_err());
+		assert!(parse_bits("0010 2").is_err());
+		assert!(parse_bits("01").is_err());
+	}
+
+	#[test]
+	fn lists_survive_encoding() {
+		let original = bits("0110 1");
+		assert_eq!(decode_bit_list(&bit_list(&original)).unwrap(), original);
+		assert_eq!(decode_bit_list(&bit_list(&[])).unwrap(), Vec::<bool>::new());
+		let bytes = b"BLC\x00\xff";
+		assert_eq!(decode_byte_list(&byte_list(bytes)).unwrap(), bytes.to_vec());
+		assert_eq!(to_bytes(&bits_from_bytes(bytes)), bytes.to_vec());
+	}
+
+	#[test]
+	fn decoding_checks_list_elements() {
+		assert!(decode_bit_list(&term("\\x.x")).is_err());
+		assert!(decode_byte_list(&bit_list(&bits("0101"))).is_err());
+	}
+}
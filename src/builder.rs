@@ -0,0 +1,131 @@
+use AstNode;
+use native::Builtin;
+
+/// A lambda term with named variables. Unlike `AstNode` it doesn't
+/// need de Bruijn indices to be worked out by hand, `build` does that.
+pub enum Term {
+	Variable(String),
+	Function(String, Box<Term>),
+	Application(Box<Term>, Box<Term>),
+	Name(String),
+	Integer(u32),
+}
+
+pub fn var(name: &str) -> Term {
+	Term::Variable(name.to_string())
+}
+
+/// Builds `\p1.\p2. ... body`.
+pub fn lam(params: &[&str], body: Term) -> Term {
+	let mut result = body;
+	for param in params.iter().rev() {
+		result = Term::Function(param.to_string(), Box::new(result));
+	}
+	result
+}
+
+pub fn app(function: Term, argument: Term) -> Term {
+	Term::Application(Box::new(function), Box::new(argument))
+}
+
+/// Reference to a named definition or an arithmetic operator,
+/// resolved by the interpreter.
+pub fn name(name: &str) -> Term {
+	Term::Name(name.to_string())
+}
+
+pub fn num(num: u32) -> Term {
+	Term::Integer(num)
+}
+
+fn is_free_variable(name: &str) -> bool {
+	let mut chars = name.chars();
+	match (chars.next(), chars.next()) {
+		(Some(ch), None) => ch.is_ascii_lowercase(),
+		_ => false,
+	}
+}
+
+fn is_name(name: &str) -> bool {
+	Builtin::from_symbol(name).is_some() || (
+		!name.is_empty() &&
+		name.chars().all(|ch: char| ch.is_ascii_uppercase() || ch.is_ascii_digit()) &&
+		!name.starts_with(|ch: char| ch.is_ascii_digit()))
+}
+
+impl Term {
+	/// Applies the term to the argument, so that applications
+	/// can be chained: `var("f").app(var("x")).app(var("y"))`.
+	pub fn app(self, argument: Term) -> Term {
+		app(self, argument)
+	}
+
+	fn build_walk(&self, scope: &mut Vec<String>) -> Result<AstNode, String> {
+		match self {
+			Term::Variable(name) => {
+				match scope.iter().rev().position(|bound| bound == name) {
+					Some(index) => Ok(AstNode::BoundVariable(index as u32)),
+					None if is_free_variable(name) =>
+						Ok(AstNode::FreeVariable(name.chars().next().unwrap())),
+					None => Err(format!("unbound variable: {}", name)),
+				}
+			},
+			Term::Function(param, body) => {
+				scope.push(param.clone());
+				let body = body.build_walk(scope);
+				scope.pop();
+				Ok(AstNode::Function(Box::new(body?)))
+			},
+			Term::Application(a, b) =>
+				Ok(AstNode::Application(
+					Box::new(a.build_walk(scope)?),
+					Box::new(b.build_walk(scope)?))),
+			Term::Name(name) if is_name(name) =>
+				Ok(AstNode::Name(name.clone())),
+			Term::Name(name) =>
+				Err(format!("invalid name: {}", name)),
+			&Term::Integer(num) =>
+				Ok(AstNode::Integer(num)),
+		}
+	}
+
+	/// Converts named variables to de Bruijn indices. Unbound variables
+	/// become free variables, so they must be single letters.
+	pub fn build(&self) -> Result<AstNode, String> {
+		self.build_walk(&mut Vec::new())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use runtime::{numeric_value, Interpreter};
+
+	fn evaluate(term: Term) -> Option<u32> {
+		let mut interpreter = Interpreter::new();
+		interpreter.set_quiet(true);
+		interpreter.load_prelude();
+		let node = term.build().unwrap();
+		numeric_value(&interpreter.evaluate(&node).unwrap())
+	}
+
+	#[test]
+	fn built_terms_evaluate() {
+		let two = lam(&["f", "x"], var("f").app(var("f").app(var("x"))));
+		assert_eq!(evaluate(two), Some(2));
+		let product = name("MUL").app(num(3)).app(name("ADD").app(num(1)).app(num(4)));
+		assert_eq!(evaluate(product), Some(15));
+	}
+
+	#[test]
+	fn inner_parameters_shadow_outer_ones() {
+		let zero = lam(&["x", "x"], var("x"));
+		assert_eq!(evaluate(zero), Some(0));
+	}
+
+	#[test]
+	fn invalid_terms_are_errors() {
+		assert_eq!(lam(&["x"], var("input")).build().err().unwrap(), "unbound variable: input");
+		assert_eq!(name("add").build().err().unwrap(), "invalid name: add");
+	}
+}
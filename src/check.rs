@@ -0,0 +1,95 @@
+use std::rc::Rc;
+use diagnostic::{Diagnostic, Source, Span};
+use parser;
+
+/// Statement that checks the program while the script runs. The
+/// positions are of the terms in the statement.
+pub enum Check {
+	/// `assert EXPR`, where the term should reduce to `TRUE`.
+	Assert(usize),
+	/// `expect EXPR == VALUE`, where both terms should have the same
+	/// normal form, up to eta-conversion. The second position is of `==`.
+	Expect(usize, usize),
+}
+
+/// Parses the statement if it is an `assert` or an `expect`.
+pub fn parse_check(source: &Rc<Source>) -> Option<Result<Check, Diagnostic>> {
+	if let Some(start) = parser::statement_keyword(&source.text, "assert") {
+		return Some(Ok(Check::Assert(start)));
+	}
+	let start = parser::statement_keyword(&source.text, "expect")?;
+	let chars: Vec<char> = source.text.chars().skip(start).collect();
+	Some(match find_separator(&chars) {
+		Some(index) => Ok(Check::Expect(start, start + index)),
+		None => {
+			let end = source.text.chars().count();
+			Err(Diagnostic::error("expected `==` in the expectation")
+				.at(Some(&Span::new(source, start, end)))
+				.help("write it like `expect ADD 1 2 == 3`"))
+		},
+	})
+}
+
+/// Finds the `==` between the two terms of an expectation. As `==`
+/// is also an operator, only one outside parentheses with something
+/// on both sides counts, so `expect == 2 2 == TRUE` compares `== 2 2`.
+fn find_separator(chars: &[char]) -> Option<usize> {
+	let is_blank = |chars: &[char]| chars.iter().all(|ch| ch.is_whitespace());
+	let mut depth = 0;
+	for index in 0..chars.len() {
+		match chars[index] {
+			'(' => depth += 1,
+			')' => depth -= 1,
+			'=' if depth == 0 && chars.get(index + 1) == Some(&'=')
+				&& !is_blank(&chars[..index]) && !is_blank(&chars[index + 2..]) =>
+				return Some(index),
+			_ => {},
+		}
+	}
+	None
+}
+
+/// Characters of the source that the terms of the check are in.
+pub fn term_ranges(check: &Check, source: &Source) -> Vec<(usize, usize)> {
+	let end = source.text.chars().count();
+	match *check {
+		Check::Assert(start) => vec![(start, end)],
+		Check::Expect(start, equals) => vec![(start, equals), (equals + 2, end)],
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn terms(text: &str) -> Vec<String> {
+		let source = Source::new(text);
+		let check = match parse_check(&source) {
+			Some(Ok(check)) => check,
+			_ => panic!("{} is not a check", text),
+		};
+		term_ranges(&check, &source).into_iter()
+			.map(|(start, end)| text.chars().skip(start).take(end - start).collect::<String>().trim().to_string())
+			.collect()
+	}
+
+	#[test]
+	fn expectations_split_at_equals() {
+		assert_eq!(terms("expect ADD 1 2 == 3"), vec!["ADD 1 2", "3"]);
+		assert_eq!(terms("assert TRUE"), vec!["TRUE"]);
+	}
+
+	#[test]
+	fn equality_operator_is_a_term() {
+		assert_eq!(terms("expect == 2 2 == TRUE"), vec!["== 2 2", "TRUE"]);
+		assert_eq!(terms("expect (== 2 2) == TRUE"), vec!["(== 2 2)", "TRUE"]);
+	}
+
+	#[test]
+	fn missing_equals_is_an_error() {
+		match parse_check(&Source::new("expect == 2 2")) {
+			Some(Err(..)) => {},
+			_ => panic!("expectation without a second term was accepted"),
+		}
+	}
+}
@@ -0,0 +1,269 @@
+use std::char;
+use std::error;
+use std::fmt;
+use AstNode;
+use parser;
+use runtime::{boolean_value, numeric_value};
+
+/// Error returned when a term doesn't encode the expected value.
+/// `context` says where in a compound value the problem was found,
+/// outermost part first.
+#[derive(Debug)]
+pub struct DecodeError {
+	pub context: Vec<String>,
+	pub message: String,
+}
+
+impl DecodeError {
+	fn new(message: &str) -> DecodeError {
+		DecodeError {
+			context: Vec::new(),
+			message: message.to_string(),
+		}
+	}
+
+	fn within(mut self, context: String) -> DecodeError {
+		self.context.insert(0, context);
+		self
+	}
+}
+
+impl fmt::Display for DecodeError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for part in self.context.iter() {
+			write!(f, "in {}: ", part)?;
+		}
+		write!(f, "{}", self.message)
+	}
+}
+
+impl error::Error for DecodeError {}
+
+/// Values that can be encoded as lambda terms, using the same
+/// encodings as the prelude.
+pub trait ToChurch {
+	fn to_church(&self) -> AstNode;
+}
+
+/// Values that can be decoded from lambda terms in normal form.
+pub trait FromChurch: Sized {
+	fn from_church(node: &AstNode) -> Result<Self, DecodeError>;
+}
+
+fn church_boolean(value: bool) -> AstNode {
+	let var = if value { 1 } else { 0 };
+	AstNode::Function(Box::new(AstNode::Function(
+		Box::new(AstNode::BoundVariable(var)))))
+}
+
+/// `NIL := \x.TRUE`
+fn nil() -> AstNode {
+	AstNode::Function(Box::new(church_boolean(true)))
+}
+
+fn is_nil(node: &AstNode) -> bool {
+	match node {
+		AstNode::Function(body) => boolean_value(body) == Some(true),
+		_ => false,
+	}
+}
+
+/// `PAIR x y` reduces to `\f.f x y`, and so does `CONS x y`.
+pub fn pair(first: AstNode, second: AstNode) -> AstNode {
+	AstNode::Function(Box::new(AstNode::Application(
+		Box::new(AstNode::Application(
+			Box::new(AstNode::BoundVariable(0)),
+			Box::new(first))),
+		Box::new(second))))
+}
+
+/// Splits a pair in normal form into its components.
+pub fn split_pair(node: &AstNode) -> Option<(&AstNode, &AstNode)> {
+	if let AstNode::Function(body) = node {
+		if let AstNode::Application(f, second) = &**body {
+			if let AstNode::Application(var, first) = &**f {
+				if let &AstNode::BoundVariable(0) = &**var {
+					return Some((&**first, &**second));
+				}
+			}
+		}
+	}
+	None
+}
+
+impl ToChurch for u32 {
+	fn to_church(&self) -> AstNode {
+		parser::create_church_numeral(*self)
+	}
+}
+
+impl FromChurch for u32 {
+	fn from_church(node: &AstNode) -> Result<u32, DecodeError> {
+		numeric_value(node).ok_or_else(|| DecodeError::new("expected a Church numeral"))
+	}
+}
+
+impl ToChurch for bool {
+	fn to_church(&self) -> AstNode {
+		church_boolean(*self)
+	}
+}
+
+impl FromChurch for bool {
+	fn from_church(node: &AstNode) -> Result<bool, DecodeError> {
+		boolean_value(node).ok_or_else(|| DecodeError::new("expected TRUE or FALSE"))
+	}
+}
+
+/// Characters are encoded as numerals of their code points.
+impl ToChurch for char {
+	fn to_church(&self) -> AstNode {
+		(*self as u32).to_church()
+	}
+}
+
+impl FromChurch for char {
+	fn from_church(node: &AstNode) -> Result<char, DecodeError> {
+		let code = u32::from_church(node)?;
+		char::from_u32(code).ok_or_else(|| DecodeError::new("expected a character code"))
+	}
+}
+
+impl<A: ToChurch, B: ToChurch> ToChurch for (A, B) {
+	fn to_church(&self) -> AstNode {
+		pair(self.0.to_church(), self.1.to_church())
+	}
+}
+
+impl<A: FromChurch, B: FromChurch> FromChurch for (A, B) {
+	fn from_church(node: &AstNode) -> Result<(A, B), DecodeError> {
+		let (first, second) = split_pair(node)
+			.ok_or_else(|| DecodeError::new("expected a pair"))?;
+		let first = A::from_church(first)
+			.map_err(|e| e.within("first element of pair".to_string()))?;
+		let second = B::from_church(second)
+			.map_err(|e| e.within("second element of pair".to_string()))?;
+		Ok((first, second))
+	}
+}
+
+/// Triples are nested pairs: `PAIR a (PAIR b c)`.
+impl<A: ToChurch, B: ToChurch, C: ToChurch> ToChurch for (A, B, C) {
+	fn to_church(&self) -> AstNode {
+		pair(self.0.to_church(), pair(self.1.to_church(), self.2.to_church()))
+	}
+}
+
+impl<A: FromChurch, B: FromChurch, C: FromChurch> FromChurch for (A, B, C) {
+	fn from_church(node: &AstNode) -> Result<(A, B, C), DecodeError> {
+		let (a, (b, c)) = <(A, (B, C))>::from_church(node)?;
+		Ok((a, b, c))
+	}
+}
+
+/// Lists are built with `CONS` and `NIL`.
+impl<T: ToChurch> ToChurch for Vec<T> {
+	fn to_church(&self) -> AstNode {
+		self.iter().rev().fold(nil(), |list, item| pair(item.to_church(), list))
+	}
+}
+
+impl<T: FromChurch> FromChurch for Vec<T> {
+	fn from_church(node: &AstNode) -> Result<Vec<T>, DecodeError> {
+		let mut items = Vec::new();
+		let mut current = node;
+		while !is_nil(current) {
+			let (head, tail) = match split_pair(current) {
+				Some(parts) => parts,
+				None if items.is_empty() => return Err(DecodeError::new("expected a list")),
+				None => return Err(DecodeError::new("expected a list")
+					.within(format!("tail after {} elements", items.len()))),
+			};
+			let item = T::from_church(head)
+				.map_err(|e| e.within(format!("list element {}", items.len() + 1)))?;
+			items.push(item);
+			current = tail;
+		}
+		Ok(items)
+	}
+}
+
+/// Strings are lists of character codes.
+impl ToChurch for str {
+	fn to_church(&self) -> AstNode {
+		self.chars().collect::<Vec<char>>().to_church()
+	}
+}
+
+impl ToChurch for String {
+	fn to_church(&self) -> AstNode {
+		self.as_str().to_church()
+	}
+}
+
+impl FromChurch for String {
+	fn from_church(node: &AstNode) -> Result<String, DecodeError> {
+		let chars = Vec::<char>::from_church(node)?;
+		Ok(chars.into_iter().collect())
+	}
+}
+
+/// Optional values are lists of at most one element, so `NULL`
+/// and `HEAD` work on them: `None` is `NIL`, `Some(x)` is `CONS x NIL`.
+impl<T: ToChurch> ToChurch for Option<T> {
+	fn to_church(&self) -> AstNode {
+		match self {
+			Some(value) => pair(value.to_church(), nil()),
+			&None => nil(),
+		}
+	}
+}
+
+impl<T: FromChurch> FromChurch for Option<T> {
+	fn from_church(node: &AstNode) -> Result<Option<T>, DecodeError> {
+		let mut items = Vec::<T>::from_church(node)?;
+		if items.len() > 1 {
+			return Err(DecodeError::new("expected a list of at most one element"));
+		}
+		Ok(items.pop())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn round_trip<T: ToChurch + FromChurch>(value: &T) -> T {
+		T::from_church(&value.to_church()).unwrap()
+	}
+
+	#[test]
+	fn numbers_and_booleans() {
+		assert_eq!(round_trip(&0u32), 0);
+		assert_eq!(round_trip(&7u32), 7);
+		assert!(round_trip(&true));
+		assert!(!round_trip(&false));
+		assert_eq!(round_trip(&'x'), 'x');
+	}
+
+	#[test]
+	fn compound_values() {
+		assert_eq!(round_trip(&(3u32, true)), (3, true));
+		assert_eq!(round_trip(&(1u32, false, 'a')), (1, false, 'a'));
+		assert_eq!(round_trip(&vec![1u32, 2, 3]), vec![1, 2, 3]);
+		assert_eq!(round_trip(&Vec::<u32>::new()), Vec::<u32>::new());
+		assert_eq!(round_trip(&"hi".to_string()), "hi");
+		assert_eq!(round_trip(&Some(4u32)), Some(4));
+		assert_eq!(round_trip(&None::<u32>), None);
+	}
+
+	#[test]
+	fn errors_say_where_decoding_failed() {
+		let list = vec![(1u32, 2u32), (3, 4)].to_church();
+		let error = Vec::<(u32, bool)>::from_church(&list).unwrap_err();
+		assert_eq!(error.to_string(), "in list element 1: in second element of pair: expected TRUE or FALSE");
+		assert_eq!(u32::from_church(&false.to_church()).unwrap(), 0);
+		assert!(bool::from_church(&2u32.to_church()).is_err());
+		assert!(Option::<u32>::from_church(&vec![1u32, 2].to_church()).is_err());
+	}
+}
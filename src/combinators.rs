@@ -0,0 +1,513 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use AstNode;
+
+/// Maximum number of combinator rewrites performed by `reduce`
+/// before giving up, so that diverging terms don't hang the REPL.
+const STEP_LIMIT: u64 = 1000000;
+
+/// The graph reducer did `STEP_LIMIT` rewrites without reaching
+/// normal form.
+#[derive(Debug)]
+pub struct OutOfSteps;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Combinator {
+	S,
+	K,
+	I,
+	B,
+	C,
+	SPrime,
+	BPrime,
+	CPrime,
+}
+
+impl Combinator {
+	pub fn name(self) -> &'static str {
+		match self {
+			Combinator::S => "S",
+			Combinator::K => "K",
+			Combinator::I => "I",
+			Combinator::B => "B",
+			Combinator::C => "C",
+			Combinator::SPrime => "S'",
+			Combinator::BPrime => "B'",
+			Combinator::CPrime => "C'",
+		}
+	}
+
+	fn arity(self) -> usize {
+		match self {
+			Combinator::I => 1,
+			Combinator::K => 2,
+			Combinator::S | Combinator::B | Combinator::C => 3,
+			Combinator::SPrime | Combinator::BPrime | Combinator::CPrime => 4,
+		}
+	}
+}
+
+/// A term built only from combinators, free variables and
+/// applications. `Variable` holds a de Bruijn index and only
+/// appears in intermediate results of bracket abstraction.
+pub enum Term {
+	Combinator(Combinator),
+	Variable(u32),
+	FreeVariable(char),
+	Application(Box<Term>, Box<Term>),
+}
+
+fn apply(a: Term, b: Term) -> Term {
+	Term::Application(Box::new(a), Box::new(b))
+}
+
+fn apply2(c: Combinator, a: Term, b: Term) -> Term {
+	apply(apply(Term::Combinator(c), a), b)
+}
+
+fn apply3(c: Combinator, a: Term, b: Term, d: Term) -> Term {
+	apply(apply2(c, a, b), d)
+}
+
+fn occurs(term: &Term, var: u32) -> bool {
+	match term {
+		&Term::Variable(v) => v == var,
+		Term::Application(a, b) => occurs(a, var) || occurs(b, var),
+		_ => false,
+	}
+}
+
+/// Removes the innermost binder from the scope of the term,
+/// which must not reference it.
+fn lower(term: Term) -> Term {
+	match term {
+		Term::Variable(v) => Term::Variable(v - 1),
+		Term::Application(a, b) => apply(lower(*a), lower(*b)),
+		other => other,
+	}
+}
+
+fn split_k(term: Term) -> Result<Term, Term> {
+	match term {
+		Term::Application(f, x) => match *f {
+			Term::Combinator(Combinator::K) => Ok(*x),
+			f => Err(apply(f, *x)),
+		},
+		other => Err(other),
+	}
+}
+
+fn split_b(term: Term) -> Result<(Term, Term), Term> {
+	match term {
+		Term::Application(f, g) => match *f {
+			Term::Application(b, c) => match *b {
+				Term::Combinator(Combinator::B) => Ok((*c, *g)),
+				b => Err(apply(apply(b, *c), *g)),
+			},
+			f => Err(apply(f, *g)),
+		},
+		other => Err(other),
+	}
+}
+
+/// Builds `S p q`, simplified with Turner's optimisation rules.
+fn combine(p: Term, q: Term) -> Term {
+	match (split_k(p), split_k(q)) {
+		// S (K p) (K q) = K (p q)
+		(Ok(p), Ok(q)) =>
+			apply(Term::Combinator(Combinator::K), apply(p, q)),
+		(Ok(p), Err(q)) => match (p, q) {
+			// S (K p) I = p
+			(p, Term::Combinator(Combinator::I)) => p,
+			// S (K (c f)) q = B' c f q
+			(Term::Application(c, f), q) =>
+				apply3(Combinator::BPrime, *c, *f, q),
+			// S (K p) q = B p q
+			(p, q) => apply2(Combinator::B, p, q),
+		},
+		(Err(p), Ok(q)) => match split_b(p) {
+			// S (B c f) (K q) = C' c f q
+			Ok((c, f)) => apply3(Combinator::CPrime, c, f, q),
+			// S p (K q) = C p q
+			Err(p) => apply2(Combinator::C, p, q),
+		},
+		(Err(p), Err(q)) => match split_b(p) {
+			// S (B c f) q = S' c f q
+			Ok((c, f)) => apply3(Combinator::SPrime, c, f, q),
+			Err(p) => apply2(Combinator::S, p, q),
+		},
+	}
+}
+
+/// Bracket abstraction: eliminates de Bruijn variable 0 from the term.
+fn abstract_variable(term: Term, compact: bool) -> Term {
+	if !occurs(&term, 0) {
+		return apply(Term::Combinator(Combinator::K), lower(term));
+	}
+
+	match term {
+		Term::Application(a, b) => {
+			let (a, b) = (*a, *b);
+			let eta = match b {
+				Term::Variable(0) => !occurs(&a, 0),
+				_ => false,
+			};
+			if compact && eta {
+				return lower(a);
+			}
+
+			let left = abstract_variable(a, compact);
+			let right = abstract_variable(b, compact);
+			if compact {
+				combine(left, right)
+			} else {
+				apply2(Combinator::S, left, right)
+			}
+		},
+		// the only variable that can occur here is 0
+		_ => Term::Combinator(Combinator::I),
+	}
+}
+
+fn compile_walk(node: &AstNode, compact: bool) -> Term {
+	match node {
+		&AstNode::FreeVariable(ch) => Term::FreeVariable(ch),
+		&AstNode::BoundVariable(v) => Term::Variable(v),
+		AstNode::Application(a, b) =>
+			apply(compile_walk(a, compact), compile_walk(b, compact)),
+		AstNode::Function(body) =>
+			abstract_variable(compile_walk(body, compact), compact),
+		&AstNode::Name(..) =>
+			panic!("name node in expression"),
+		&AstNode::Spanned(..) =>
+			panic!("spanned node in expression"),
+		&AstNode::Origin(..) =>
+			panic!("origin node in expression"),
+		&AstNode::Integer(..) | &AstNode::Builtin(..) | &AstNode::Foreign(..) =>
+			panic!("native value in expression"),
+	}
+}
+
+/// Translates a closed lambda term into combinators. Without `compact`
+/// only S, K and I are used, otherwise Turner's B, C, S', B' and C'
+/// combinators are introduced as well.
+pub fn compile(node: &AstNode, compact: bool) -> Term {
+	compile_walk(node, compact)
+}
+
+pub fn term_size(term: &Term) -> usize {
+	match term {
+		Term::Application(a, b) => 1 + term_size(a) + term_size(b),
+		_ => 1,
+	}
+}
+
+pub fn lambda_size(node: &AstNode) -> usize {
+	match node {
+		AstNode::Application(a, b) => 1 + lambda_size(a) + lambda_size(b),
+		AstNode::Function(body) => 1 + lambda_size(body),
+		_ => 1,
+	}
+}
+
+fn pretty_print_walk(term: &Term, in_argument: bool) {
+	match term {
+		&Term::Combinator(c) => print!("{}", c.name()),
+		&Term::Variable(v) => print!("{}", v),
+		&Term::FreeVariable(ch) => print!("{}", ch),
+		Term::Application(a, b) => {
+			if in_argument {
+				print!("(");
+			}
+			pretty_print_walk(a, false);
+			print!(" ");
+			pretty_print_walk(b, true);
+			if in_argument {
+				print!(")");
+			}
+		},
+	}
+}
+
+pub fn pretty_print(term: &Term) {
+	pretty_print_walk(term, false);
+}
+
+enum Cell {
+	Application(Graph, Graph),
+	Combinator(Combinator),
+	FreeVariable(char),
+	/// Opaque atom used to probe the behaviour of a reduced graph.
+	Probe(u32),
+	/// Left behind in place of `I x` and `K x y` redexes.
+	Indirection(Graph),
+}
+
+type Graph = Rc<RefCell<Cell>>;
+
+fn cell(contents: Cell) -> Graph {
+	Rc::new(RefCell::new(contents))
+}
+
+fn graph_apply(a: &Graph, b: &Graph) -> Graph {
+	cell(Cell::Application(a.clone(), b.clone()))
+}
+
+fn build_graph(term: &Term) -> Graph {
+	match term {
+		&Term::Combinator(c) => cell(Cell::Combinator(c)),
+		&Term::FreeVariable(ch) => cell(Cell::FreeVariable(ch)),
+		&Term::Variable(..) => panic!("bound variable in combinator graph"),
+		Term::Application(a, b) =>
+			cell(Cell::Application(build_graph(a), build_graph(b))),
+	}
+}
+
+fn follow_indirections(node: &Graph) -> Graph {
+	let mut current = node.clone();
+	loop {
+		let next = match *current.borrow() {
+			Cell::Indirection(ref target) => target.clone(),
+			_ => break,
+		};
+		current = next;
+	}
+	current
+}
+
+fn left_child(node: &Graph) -> Option<Graph> {
+	match *node.borrow() {
+		Cell::Application(ref f, _) => Some(f.clone()),
+		_ => None,
+	}
+}
+
+fn right_child(node: &Graph) -> Graph {
+	match *node.borrow() {
+		Cell::Application(_, ref x) => x.clone(),
+		_ => panic!("spine node is not an application"),
+	}
+}
+
+fn rewrite(c: Combinator, args: &[Graph]) -> Cell {
+	match c {
+		Combinator::I => Cell::Indirection(args[0].clone()),
+		Combinator::K => Cell::Indirection(args[0].clone()),
+		Combinator::S => Cell::Application(
+			graph_apply(&args[0], &args[2]),
+			graph_apply(&args[1], &args[2])),
+		Combinator::B => Cell::Application(
+			args[0].clone(),
+			graph_apply(&args[1], &args[2])),
+		Combinator::C => Cell::Application(
+			graph_apply(&args[0], &args[2]),
+			args[1].clone()),
+		Combinator::SPrime => Cell::Application(
+			graph_apply(&args[0], &graph_apply(&args[1], &args[3])),
+			graph_apply(&args[2], &args[3])),
+		Combinator::BPrime => Cell::Application(
+			graph_apply(&args[0], &args[1]),
+			graph_apply(&args[2], &args[3])),
+		Combinator::CPrime => Cell::Application(
+			graph_apply(&args[0], &graph_apply(&args[1], &args[3])),
+			args[2].clone()),
+	}
+}
+
+/// Reduces the graph to weak head normal form by unwinding its spine
+/// and overwriting redexes in place. Returns the application nodes
+/// of the final spine, outermost first.
+fn reduce_head(node: &Graph, steps: &mut u64) -> Result<Vec<Graph>, OutOfSteps> {
+	let mut spine: Vec<Graph> = Vec::new();
+	let mut current = follow_indirections(node);
+	loop {
+		if let Some(f) = left_child(&current) {
+			let target = follow_indirections(&f);
+			if !Rc::ptr_eq(&f, &target) {
+				// short-circuit the indirection so that chains
+				// of them don't build up in looping programs
+				if let Cell::Application(ref mut f, _) = *current.borrow_mut() {
+					*f = target.clone();
+				}
+			}
+			spine.push(current);
+			current = target;
+			continue;
+		}
+
+		let combinator = match *current.borrow() {
+			Cell::Combinator(c) if spine.len() >= c.arity() => c,
+			_ => return Ok(spine),
+		};
+
+		if *steps >= STEP_LIMIT {
+			return Err(OutOfSteps);
+		}
+		*steps += 1;
+
+		let arity = combinator.arity();
+		let root_index = spine.len() - arity;
+		let args: Vec<Graph> = spine[root_index..].iter()
+			.rev()
+			.map(|app| follow_indirections(&right_child(app)))
+			.collect();
+		let root = spine[root_index].clone();
+		*root.borrow_mut() = rewrite(combinator, &args);
+		spine.truncate(root_index);
+		current = follow_indirections(&root);
+	}
+}
+
+fn reduce_graph(node: &Graph, steps: &mut u64) -> Result<(), OutOfSteps> {
+	let spine = reduce_head(node, steps)?;
+	for app in spine.iter() {
+		reduce_graph(&right_child(app), steps)?;
+	}
+	Ok(())
+}
+
+fn read_back(node: &Graph) -> Term {
+	let node = follow_indirections(node);
+	let contents = node.borrow();
+	match *contents {
+		Cell::Application(ref a, ref b) => apply(read_back(a), read_back(b)),
+		Cell::Combinator(c) => Term::Combinator(c),
+		Cell::FreeVariable(ch) => Term::FreeVariable(ch),
+		Cell::Probe(..) => panic!("probe in combinator graph"),
+		Cell::Indirection(..) => unreachable!(),
+	}
+}
+
+/// Counts `f` applications in a graph of the form `f (f (... (f x)))`,
+/// where `f` and `x` are probes 0 and 1.
+fn probe_count(node: &Graph) -> Option<u32> {
+	let mut result = 0;
+	let mut current = follow_indirections(node);
+	loop {
+		let next = match *current.borrow() {
+			Cell::Probe(1) => return Some(result),
+			Cell::Application(ref f, ref x) => {
+				match *follow_indirections(f).borrow() {
+					Cell::Probe(0) => (),
+					_ => return None,
+				}
+				follow_indirections(x)
+			},
+			_ => return None,
+		};
+		result += 1;
+		current = next;
+	}
+}
+
+/// Result of reducing a combinator term through the graph reducer.
+pub struct Evaluation {
+	pub normal_form: Term,
+	pub steps: u64,
+	graph: Graph,
+}
+
+impl Evaluation {
+	/// Applies the normal form to two opaque atoms `f` and `x`. Church
+	/// numerals reduce to `f (f ... x)`, while `TRUE` reduces to `f`
+	/// and `FALSE` (which is also 0) reduces to `x`.
+	pub fn church_value(&self) -> Result<ChurchValue, OutOfSteps> {
+		let probe = graph_apply(
+			&graph_apply(&self.graph, &cell(Cell::Probe(0))),
+			&cell(Cell::Probe(1)));
+		let mut steps = 0;
+		reduce_graph(&probe, &mut steps)?;
+
+		let probe = follow_indirections(&probe);
+		if let Cell::Probe(0) = *probe.borrow() {
+			return Ok(ChurchValue::True);
+		}
+		match probe_count(&probe) {
+			Some(num) => Ok(ChurchValue::Number(num)),
+			None => Ok(ChurchValue::Unknown),
+		}
+	}
+}
+
+pub enum ChurchValue {
+	Number(u32),
+	True,
+	Unknown,
+}
+
+/// Reduces the term to normal form using combinator graph reduction.
+/// Fails if the term doesn't reach normal form within the step limit.
+pub fn reduce(term: &Term) -> Result<Evaluation, OutOfSteps> {
+	let graph = build_graph(term);
+	let mut steps = 0;
+	reduce_graph(&graph, &mut steps)?;
+	Ok(Evaluation {
+		normal_form: read_back(&graph),
+		steps,
+		graph,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use reduction;
+	use runtime::{boolean_value, numeric_value, Interpreter};
+
+	fn prelude_term(source: &str) -> AstNode {
+		let mut interpreter = Interpreter::new();
+		interpreter.set_quiet(true);
+		interpreter.load_prelude();
+		interpreter.parse_expression(source).unwrap()
+	}
+
+	fn uses(term: &Term, combinator: Combinator) -> bool {
+		match term {
+			&Term::Combinator(c) => c == combinator,
+			Term::Application(a, b) => uses(a, combinator) || uses(b, combinator),
+			_ => false,
+		}
+	}
+
+	/// Checks that the graph reducer gives the same Church value
+	/// as reducing the lambda term.
+	fn agrees_with_beta_reduction(source: &str) {
+		let node = prelude_term(source);
+		let normal_form = reduction::beta_reduce(&node).unwrap();
+		for &compact in [false, true].iter() {
+			let evaluation = reduce(&compile(&node, compact)).ok().unwrap();
+			match evaluation.church_value().ok().unwrap() {
+				ChurchValue::Number(num) => assert_eq!(numeric_value(&normal_form), Some(num), "{}", source),
+				ChurchValue::True => assert_eq!(boolean_value(&normal_form), Some(true), "{}", source),
+				ChurchValue::Unknown => panic!("{} has no Church value", source),
+			}
+		}
+	}
+
+	#[test]
+	fn church_values_agree_with_beta_reduction() {
+		agrees_with_beta_reduction("ADD 2 3");
+		agrees_with_beta_reduction("MUL 2 (PRED 4)");
+		agrees_with_beta_reduction("TRUE");
+		agrees_with_beta_reduction("AND TRUE FALSE");
+		agrees_with_beta_reduction("FIRST (PAIR 4 TRUE)");
+	}
+
+	#[test]
+	fn compact_compilation_uses_b_and_c() {
+		let compose = prelude_term("\\f.\\g.\\x.f (g x)");
+		let plain = compile(&compose, false);
+		assert!(uses(&plain, Combinator::S) && !uses(&plain, Combinator::B));
+		let compact = compile(&compose, true);
+		assert!(uses(&compact, Combinator::B));
+		assert!(term_size(&compact) < term_size(&plain));
+		let flip = compile(&prelude_term("\\f.\\x.\\y.f y x"), true);
+		assert!(uses(&flip, Combinator::C));
+	}
+
+	#[test]
+	fn diverging_terms_hit_the_step_limit() {
+		let omega = compile(&prelude_term("(\\x.x x) (\\x.x x)"), true);
+		assert!(reduce(&omega).is_err());
+	}
+}
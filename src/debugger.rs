@@ -0,0 +1,328 @@
+use std::collections::HashSet;
+use std::mem;
+use std::rc::Rc;
+use AstNode;
+use printer::{self, Pretty, Style};
+use reduction;
+
+/// Number of steps `next` takes before giving up, for subterms
+/// that have no normal form.
+const NEXT_FUEL: usize = 10000;
+
+/// One move from a term to its subterm.
+#[derive(Clone, Copy)]
+enum Branch {
+	/// Into the body of a function, or the function of an origin.
+	Inside,
+	Function,
+	Argument,
+}
+
+/// Path to the leftmost outermost redex, which is the one normal
+/// order reduction contracts next.
+fn find_redex(node: &AstNode) -> Option<Vec<Branch>> {
+	let mut path = Vec::new();
+	if find_redex_walk(node, &mut path) {
+		Some(path)
+	} else {
+		None
+	}
+}
+
+fn find_redex_walk(node: &AstNode, path: &mut Vec<Branch>) -> bool {
+	match node {
+		AstNode::Application(a, b) => {
+			if reduction::contract(node).is_some() {
+				return true;
+			}
+			for &(branch, node) in [(Branch::Function, a), (Branch::Argument, b)].iter() {
+				path.push(branch);
+				if find_redex_walk(node, path) {
+					return true;
+				}
+				path.pop();
+			}
+			false
+		},
+		&AstNode::Function(ref body) |
+		&AstNode::Origin(_, ref body) => {
+			path.push(Branch::Inside);
+			if find_redex_walk(body, path) {
+				return true;
+			}
+			path.pop();
+			false
+		},
+		_ => false,
+	}
+}
+
+/// The subterm at the end of the path, with the number of
+/// binders it is under.
+fn subterm<'a>(node: &'a AstNode, path: &[Branch]) -> (&'a AstNode, u32) {
+	let mut node = node;
+	let mut depth = 0;
+	for &branch in path {
+		node = match (branch, node) {
+			(Branch::Inside, AstNode::Function(body)) => {
+				depth += 1;
+				&**body
+			},
+			(Branch::Inside, AstNode::Origin(_, node)) => &**node,
+			(Branch::Function, AstNode::Application(a, _)) => &**a,
+			(Branch::Argument, AstNode::Application(_, b)) => &**b,
+			_ => panic!("path doesn't fit the term"),
+		};
+	}
+	(node, depth)
+}
+
+/// Copies the term with the subterm at the end of the path replaced.
+fn replace(node: &AstNode, path: &[Branch], replacement: AstNode) -> AstNode {
+	let (branch, rest) = match path.split_first() {
+		Some((&branch, rest)) => (branch, rest),
+		None => return replacement,
+	};
+	match (branch, node) {
+		(Branch::Inside, AstNode::Function(body)) =>
+			AstNode::Function(Box::new(replace(body, rest, replacement))),
+		(Branch::Inside, AstNode::Origin(name, node)) =>
+			AstNode::Origin(name.clone(), Box::new(replace(node, rest, replacement))),
+		(Branch::Function, AstNode::Application(a, b)) =>
+			AstNode::Application(Box::new(replace(a, rest, replacement)), b.clone()),
+		(Branch::Argument, AstNode::Application(a, b)) =>
+			AstNode::Application(a.clone(), Box::new(replace(b, rest, replacement))),
+		_ => panic!("path doesn't fit the term"),
+	}
+}
+
+/// Definition that the function of the redex came from.
+fn head_origin(redex: &AstNode) -> Option<&Rc<str>> {
+	match redex {
+		AstNode::Application(a, _) => match **a {
+			AstNode::Origin(ref name, _) => Some(name),
+			_ => None,
+		},
+		_ => None,
+	}
+}
+
+/// A term being reduced one step at a time, see `:debug`. Its
+/// functions are tagged with the definitions they came from, so
+/// that the reduction can stop at them.
+pub struct Debugger {
+	term: AstNode,
+	/// Terms before each step, most recent last.
+	history: Vec<AstNode>,
+	breakpoints: HashSet<String>,
+	style: Style,
+	width: Option<usize>,
+}
+
+impl Debugger {
+	pub fn new(term: AstNode, style: Style, width: Option<usize>) -> Debugger {
+		Debugger {
+			term,
+			history: Vec::new(),
+			breakpoints: HashSet::new(),
+			style,
+			width,
+		}
+	}
+
+	/// Prints the current term.
+	pub fn show(&self) {
+		let term = reduction::strip_origins(&self.term);
+		println!("{}", Pretty { node: &term, style: self.style, width: self.width });
+	}
+
+	/// Runs a debugger command, returning if it succeeded.
+	pub fn command(&mut self, line: &str) -> bool {
+		let mut words = line.split_whitespace();
+		match (words.next(), words.next(), words.next()) {
+			(Some("step"), None, _) => self.step(),
+			(Some("next"), None, _) => self.next(),
+			(Some("break"), None, _) => {
+				let mut names: Vec<&String> = self.breakpoints.iter().collect();
+				names.sort();
+				if names.is_empty() {
+					println!("no breakpoints");
+				}
+				for name in names {
+					println!("breakpoint at {}", name);
+				}
+				true
+			},
+			(Some("break"), Some(name), None) => {
+				self.breakpoints.insert(name.to_string());
+				println!("breakpoint at {}", name);
+				true
+			},
+			(Some("back"), None, _) => self.back(),
+			(Some("where"), None, _) => self.show_redex(),
+			_ => {
+				println!("[Error] unknown debugger command: {}", line.trim());
+				println!("expected step, next, break NAME, back, where, or quit");
+				false
+			},
+		}
+	}
+
+	/// Contracts the next redex.
+	fn step(&mut self) -> bool {
+		let path = match find_redex(&self.term) {
+			Some(path) => path,
+			None => {
+				println!("the term is in normal form");
+				return false;
+			},
+		};
+		self.contract_at(&path);
+		self.show();
+		true
+	}
+
+	fn contract_at(&mut self, path: &[Branch]) {
+		let reduct = reduction::contract(subterm(&self.term, path).0)
+			.expect("path doesn't lead to a redex");
+		let term = replace(&self.term, path, reduct);
+		self.history.push(mem::replace(&mut self.term, term));
+	}
+
+	/// Reduces the subterm where the next redex is to normal form,
+	/// stopping early when a function from a definition with a
+	/// breakpoint gets applied.
+	fn next(&mut self) -> bool {
+		let path = match find_redex(&self.term) {
+			Some(path) => path,
+			None => {
+				println!("the term is in normal form");
+				return false;
+			},
+		};
+		let mut previous = None;
+		for steps in 0..NEXT_FUEL {
+			let inner = match find_redex(subterm(&self.term, &path).0) {
+				Some(inner) => inner,
+				None => break,
+			};
+			let mut redex_path = path.clone();
+			redex_path.extend(inner);
+			let origin = head_origin(subterm(&self.term, &redex_path).0).cloned();
+			// the remaining functions of a definition are tagged with
+			// it too, so only stop when reduction enters it
+			if let Some(ref name) = origin {
+				if steps > 0 && previous.as_ref() != Some(name) && self.breakpoints.contains(&**name) {
+					let plural = if steps == 1 { "" } else { "s" };
+					println!("stopped at {} after {} step{}", name, steps, plural);
+					self.show();
+					return true;
+				}
+			}
+			self.contract_at(&redex_path);
+			previous = origin;
+		}
+		let finished = find_redex(subterm(&self.term, &path).0).is_none();
+		if !finished {
+			println!("[Error] no normal form within {} steps", NEXT_FUEL);
+		}
+		self.show();
+		finished
+	}
+
+	/// Undoes the last step.
+	fn back(&mut self) -> bool {
+		match self.history.pop() {
+			Some(term) => {
+				self.term = term;
+				self.show();
+				true
+			},
+			None => {
+				println!("[Error] there is no step to undo");
+				false
+			},
+		}
+	}
+
+	/// Prints the next redex, where its function came from, and the
+	/// term around it with the redex left out.
+	fn show_redex(&self) -> bool {
+		let path = match find_redex(&self.term) {
+			Some(path) => path,
+			None => {
+				println!("the term is in normal form");
+				return true;
+			},
+		};
+		let renderer = self.style.renderer();
+		let (redex, depth) = subterm(&self.term, &path);
+		let plain = reduction::strip_origins(redex);
+		println!("redex:   {}", printer::render_under(&plain, depth, renderer));
+		if let Some(name) = head_origin(redex) {
+			println!("from:    {}", name);
+		}
+		if !path.is_empty() {
+			let context = replace(&self.term, &path, AstNode::Name("[]".to_string()));
+			println!("context: {}", printer::render(&reduction::strip_origins(&context), renderer));
+		}
+		println!("steps:   {}", self.history.len());
+		true
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use runtime::Interpreter;
+	use output;
+
+	/// Runs the lines in a debugging session, returning what each of
+	/// them printed.
+	fn session(lines: &[&str]) -> Vec<(bool, String)> {
+		let mut interpreter = Interpreter::new();
+		interpreter.set_quiet(true);
+		interpreter.load_prelude();
+		lines.iter().map(|line| output::capture(|| interpreter.eval_line(line))).collect()
+	}
+
+	#[test]
+	fn stepping_and_going_back() {
+		let outputs = session(&[":debug NOT TRUE", "step", "back", "back", "quit"]);
+		assert_eq!(outputs[0].1, "debugging, commands are step, next, break NAME, back, where, and quit\n\
+			(\\a.\\b.\\c.a c b) (\\a.\\b.a)\n");
+		assert_eq!(outputs[1], (true, "\\a.\\b.(\\c.\\d.c) b a\n".to_string()));
+		assert_eq!(outputs[2], (true, "(\\a.\\b.\\c.a c b) (\\a.\\b.a)\n".to_string()));
+		assert_eq!(outputs[3], (false, "[Error] there is no step to undo\n".to_string()));
+		assert_eq!(outputs[4], (true, String::new()));
+	}
+
+	#[test]
+	fn next_stops_at_breakpoints() {
+		let outputs = session(&[":debug I (NOT TRUE) y", "break NOT", "next", "where", "next", "back", "step", "where"]);
+		assert_eq!(outputs[1].1, "breakpoint at NOT\n");
+		assert_eq!(outputs[2], (true, "stopped at NOT after 1 step\n(\\a.\\b.\\c.a c b) (\\a.\\b.a) y\n".to_string()));
+		assert_eq!(outputs[3].1, "redex:   (\\a.\\b.\\c.a c b) (\\a.\\b.a)\n\
+			from:    NOT\n\
+			context: [] y\n\
+			steps:   1\n");
+		// the rest of NOT TRUE is reduced without stopping
+		assert_eq!(outputs[4], (true, "(\\a.\\b.b) y\n".to_string()));
+		assert_eq!(outputs[5].1, "(\\a.\\b.(\\c.b) a) y\n");
+		assert_eq!(outputs[6].1, "\\a.(\\b.a) y\n");
+		assert_eq!(outputs[7].1, "redex:   (\\b.a) y\n\
+			from:    TRUE\n\
+			context: \\a.[]\n\
+			steps:   4\n");
+	}
+
+	#[test]
+	fn unknown_commands_and_normal_forms() {
+		let outputs = session(&[":debug y", "foo", "step", "next", "where", "break"]);
+		assert_eq!(outputs[1], (false, "[Error] unknown debugger command: foo\n\
+			expected step, next, break NAME, back, where, or quit\n".to_string()));
+		assert_eq!(outputs[2], (false, "the term is in normal form\n".to_string()));
+		assert_eq!(outputs[3], (false, "the term is in normal form\n".to_string()));
+		assert_eq!(outputs[4], (true, "the term is in normal form\n".to_string()));
+		assert_eq!(outputs[5], (true, "no breakpoints\n".to_string()));
+	}
+}
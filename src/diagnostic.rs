@@ -0,0 +1,309 @@
+use std::fmt;
+use std::rc::Rc;
+
+/// Text that spans point into: a line typed into the interpreter,
+/// or a statement of a script.
+pub struct Source {
+	/// File the text comes from, if any.
+	pub file: Option<String>,
+	/// Line of the file the text starts on, counting from 1.
+	pub first_line: usize,
+	pub text: String,
+}
+
+impl Source {
+	pub fn new(text: &str) -> Rc<Source> {
+		Rc::new(Source {
+			file: None,
+			first_line: 1,
+			text: text.to_string(),
+		})
+	}
+
+	pub fn in_file(file: &str, first_line: usize, text: &str) -> Rc<Source> {
+		Rc::new(Source {
+			file: Some(file.to_string()),
+			first_line,
+			text: text.to_string(),
+		})
+	}
+
+	/// Line and column of a character position. Columns count
+	/// from 1, because editors do that too.
+	pub fn locate(&self, position: usize) -> (usize, usize) {
+		let mut line = self.first_line;
+		let mut column = 1;
+		for ch in self.text.chars().take(position) {
+			if ch == '\n' {
+				line += 1;
+				column = 1;
+			} else {
+				column += 1;
+			}
+		}
+		(line, column)
+	}
+}
+
+/// Characters `start..end` of a source.
+#[derive(Clone)]
+pub struct Span {
+	pub source: Rc<Source>,
+	pub start: usize,
+	pub end: usize,
+}
+
+impl Span {
+	pub fn new(source: &Rc<Source>, start: usize, end: usize) -> Span {
+		Span {
+			source: source.clone(),
+			start,
+			end,
+		}
+	}
+
+	/// Span from the start of this one to the end of the other.
+	pub fn to(&self, other: &Span) -> Span {
+		Span::new(&self.source, self.start, other.end)
+	}
+
+	/// The spanned text.
+	pub fn text(&self) -> String {
+		self.source.text.chars().skip(self.start).take(self.end - self.start).collect()
+	}
+
+	/// Place of the span as `file:line:column`, or as
+	/// `line L, column C` for text that isn't from a file.
+	pub fn location(&self) -> String {
+		let (line, column) = self.source.locate(self.start);
+		match self.source.file {
+			Some(ref file) => format!("{}:{}:{}", file, line, column),
+			None => format!("line {}, column {}", line, column),
+		}
+	}
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Severity {
+	Error,
+	Warning,
+}
+
+/// Line numbers of the first and the last line the span touches.
+fn line_range(span: &Span) -> (usize, usize) {
+	let (first, _) = span.source.locate(span.start);
+	let (last, _) = span.source.locate(if span.end > span.start { span.end - 1 } else { span.end });
+	(first, last)
+}
+
+/// Writes the lines the marks touch, each mark underlining its
+/// span with its marker and putting its label after it. The spans
+/// must all be in the same source.
+fn write_snippet(f: &mut fmt::Formatter, marks: &[(&Span, char, &str)], gutter: usize) -> fmt::Result {
+	let source = &marks[0].0.source;
+	let touches = |number: usize| marks.iter().any(|mark| {
+		let (first, last) = line_range(mark.0);
+		number >= first && number <= last
+	});
+	let mut position = 0;
+	let mut previous = None;
+	for (index, line) in source.text.split('\n').enumerate() {
+		let number = source.first_line + index;
+		let length = line.chars().count();
+		if touches(number) {
+			if previous.is_some_and(|previous| previous + 1 < number) {
+				write!(f, "\n{:gutter$} ...", "", gutter = gutter)?;
+			}
+			previous = Some(number);
+			let line = line.trim_end_matches('\r');
+			write!(f, "\n{:gutter$} | {}", number, line, gutter = gutter)?;
+			
+			// keep tabs so that markers line up
+			let mut markers: Vec<char> = line.chars()
+				.map(|ch| if ch == '\t' { '\t' } else { ' ' })
+				.collect();
+			let mut labels = Vec::new();
+			for &(span, marker, label) in marks.iter() {
+				let (first, last) = line_range(span);
+				if number < first || number > last {
+					continue;
+				}
+				let start = span.start.max(position) - position;
+				let end = span.end.min(position + length) - position;
+				let end = if end > start { end } else { start + 1 };
+				if markers.len() < end {
+					markers.resize(end, ' ');
+				}
+				for slot in markers[start..end].iter_mut() {
+					*slot = marker;
+				}
+				if number == last && !label.is_empty() {
+					labels.push(label);
+				}
+			}
+			let markers: String = markers.into_iter().collect();
+			write!(f, "\n{:gutter$} | {}", "", markers.trim_end(), gutter = gutter)?;
+			for label in labels {
+				write!(f, " {}", label)?;
+			}
+		}
+		position += length + 1;
+	}
+	Ok(())
+}
+
+/// A message about the program, shown with the lines it is
+/// about and the spanned part underlined:
+///
+/// ```text
+/// [Error] expected name, letter, number, (, or )
+///  --> list.lam:3:14
+///   |
+/// 3 | SUM := (ADD 1
+///   |        -     ^ unbalanced `(` opened here
+/// ```
+pub struct Diagnostic {
+	pub severity: Severity,
+	pub message: String,
+	pub span: Option<Span>,
+	/// Other places the diagnostic is about.
+	pub labels: Vec<(Span, String)>,
+	/// Notes and help shown last, like `note: ...`.
+	pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+	pub fn error<S: Into<String>>(message: S) -> Diagnostic {
+		Diagnostic {
+			severity: Severity::Error,
+			message: message.into(),
+			span: None,
+			labels: Vec::new(),
+			notes: Vec::new(),
+		}
+	}
+
+	pub fn warning<S: Into<String>>(message: S) -> Diagnostic {
+		Diagnostic {
+			severity: Severity::Warning,
+			..Diagnostic::error(message)
+		}
+	}
+
+	/// Points the diagnostic at the span, unless it already
+	/// points somewhere more precise.
+	pub fn at(mut self, span: Option<&Span>) -> Diagnostic {
+		if self.span.is_none() {
+			self.span = span.cloned();
+		}
+		self
+	}
+
+	pub fn label<S: Into<String>>(mut self, span: Span, label: S) -> Diagnostic {
+		self.labels.push((span, label.into()));
+		self
+	}
+
+	pub fn note<S: Into<String>>(mut self, note: S) -> Diagnostic {
+		self.notes.push(format!("note: {}", note.into()));
+		self
+	}
+
+	/// A suggestion on how to fix the problem.
+	pub fn help<S: Into<String>>(mut self, help: S) -> Diagnostic {
+		self.notes.push(format!("help: {}", help.into()));
+		self
+	}
+}
+
+impl fmt::Display for Diagnostic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let severity = match self.severity {
+			Severity::Error => "Error",
+			Severity::Warning => "Warning",
+		};
+		write!(f, "[{}] {}", severity, self.message)?;
+
+		let gutter = self.span.iter()
+			.chain(self.labels.iter().map(|label| &label.0))
+			.map(|span| line_range(span).1.to_string().len())
+			.max()
+			.unwrap_or(0);
+		if let Some(ref span) = self.span {
+			write!(f, "\n{:gutter$}--> {}", "", span.location(), gutter = gutter)?;
+			write!(f, "\n{:gutter$} |", "", gutter = gutter)?;
+			// labels in the same source are shown together with the span
+			let (together, apart): (Vec<_>, Vec<_>) = self.labels.iter()
+				.partition(|label| Rc::ptr_eq(&label.0.source, &span.source));
+			let mut marks = vec![(span, '^', "")];
+			marks.extend(together.iter().map(|label| (&label.0, '-', &*label.1)));
+			write_snippet(f, &marks, gutter)?;
+			for label in apart {
+				write!(f, "\n{:gutter$}--> {}", "", label.0.location(), gutter = gutter)?;
+				write!(f, "\n{:gutter$} |", "", gutter = gutter)?;
+				write_snippet(f, &[(&label.0, '-', &*label.1)], gutter)?;
+			}
+		}
+		for note in self.notes.iter() {
+			write!(f, "\n{:gutter$} = {}", "", note, gutter = gutter)?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn messages_without_spans() {
+		let diagnostic = Diagnostic::warning("unused definition").note("it is never used");
+		assert_eq!(diagnostic.to_string(), "[Warning] unused definition\n = note: it is never used");
+	}
+
+	#[test]
+	fn markers_line_up_with_tabs() {
+		let source = Source::in_file("tabs.lam", 4, "A :=\tFOO\tx");
+		let diagnostic = Diagnostic::error("unknown function: FOO")
+			.at(Some(&Span::new(&source, 5, 8)))
+			.help("define it first");
+		assert_eq!(diagnostic.to_string(), "[Error] unknown function: FOO\n \
+			--> tabs.lam:4:6\n  \
+			|\n\
+			4 | A :=\tFOO\tx\n  \
+			|     \t^^^\n  \
+			= help: define it first");
+	}
+
+	#[test]
+	fn spans_over_several_lines() {
+		let source = Source::new("SUM := (ADD 1\n  2\n\n\n  3");
+		let open = Span::new(&source, 7, 8);
+		let diagnostic = Diagnostic::error("expected )")
+			.at(Some(&Span::new(&source, 12, 17)))
+			.label(open, "opened here");
+		assert_eq!(diagnostic.to_string(), "[Error] expected )\n \
+			--> line 1, column 13\n  \
+			|\n\
+			1 | SUM := (ADD 1\n  \
+			|        -    ^ opened here\n\
+			2 |   2\n  \
+			| ^^^");
+	}
+
+	#[test]
+	fn distant_lines_are_elided() {
+		let source = Source::in_file("long.lam", 9, "A := B\n\n\nB := A");
+		let diagnostic = Diagnostic::warning("definitions depend on each other")
+			.at(Some(&Span::new(&source, 0, 1)))
+			.label(Span::new(&source, 14, 15), "used here");
+		assert_eq!(diagnostic.to_string(), "[Warning] definitions depend on each other\n  \
+			--> long.lam:9:1\n   \
+			|\n \
+			9 | A := B\n   \
+			| ^\n   \
+			...\n\
+			12 | B := A\n   \
+			|      - used here");
+	}
+}
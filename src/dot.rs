@@ -0,0 +1,204 @@
+use std::char;
+use std::collections::HashMap;
+use AstNode;
+use pretty_string;
+use reduction;
+
+/// Reduction graphs stop growing after this many terms.
+pub const MAX_TERMS: usize = 100;
+
+fn escape(label: &str) -> String {
+	label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Same names as `pretty_print` gives to parameters.
+fn param_name(depth: usize) -> char {
+	char::from_u32(depth as u32 + ('a' as u32)).unwrap_or('?')
+}
+
+fn term_walk(node: &AstNode, binders: &mut Vec<usize>, next_id: &mut usize,
+             lines: &mut Vec<String>) -> usize {
+	let node = node.unspanned();
+	let id = *next_id;
+	*next_id += 1;
+	let label = match node {
+		&AstNode::Function(..) => format!("λ{}", param_name(binders.len())),
+		&AstNode::Application(..) => "@".to_string(),
+		&AstNode::BoundVariable(num) => match binders.len().checked_sub(num as usize + 1) {
+			Some(depth) => param_name(depth).to_string(),
+			None => "?".to_string(),
+		},
+		&AstNode::FreeVariable(ch) => ch.to_string(),
+		AstNode::Name(name) => name.clone(),
+		&AstNode::Integer(num) => num.to_string(),
+		&AstNode::Builtin(builtin) => builtin.symbol().to_string(),
+		AstNode::Foreign(primitive) => primitive.name.clone(),
+		&AstNode::Spanned(..) |
+		&AstNode::Origin(..) => unreachable!(),
+	};
+	let shape = match node {
+		&AstNode::Function(..) | &AstNode::Application(..) => "circle",
+		_ => "plaintext",
+	};
+	lines.push(format!("\tn{} [label=\"{}\", shape={}];", id, escape(&label), shape));
+
+	match node {
+		AstNode::Function(body) => {
+			binders.push(id);
+			let child = term_walk(body, binders, next_id, lines);
+			binders.pop();
+			lines.push(format!("\tn{} -> n{};", id, child));
+		},
+		AstNode::Application(a, b) => {
+			let left = term_walk(a, binders, next_id, lines);
+			let right = term_walk(b, binders, next_id, lines);
+			lines.push(format!("\tn{} -> n{};", id, left));
+			lines.push(format!("\tn{} -> n{};", id, right));
+		},
+		&AstNode::BoundVariable(num) => {
+			if let Some(depth) = binders.len().checked_sub(num as usize + 1) {
+				// back-edge to the binder, kept out of the tree layout
+				lines.push(format!("\tn{} -> n{} [style=dashed, constraint=false];",
+				                   id, binders[depth]));
+			}
+		},
+		_ => {},
+	}
+	id
+}
+
+/// Renders the syntax tree of the term as a Graphviz graph, with
+/// dashed edges from bound variables to their lambdas.
+pub fn term_graph(node: &AstNode) -> String {
+	let mut lines = Vec::new();
+	term_walk(node, &mut Vec::new(), &mut 0, &mut lines);
+	format!("digraph term {{\n\tordering=out;\n{}\n}}\n", lines.join("\n"))
+}
+
+/// Writes the term with de Bruijn indices, so that equal
+/// terms get equal keys.
+fn key(node: &AstNode, out: &mut String) {
+	match node {
+		AstNode::Function(body) => {
+			out.push('\\');
+			key(body, out);
+		},
+		AstNode::Application(a, b) => {
+			out.push('(');
+			key(a, out);
+			out.push(' ');
+			key(b, out);
+			out.push(')');
+		},
+		&AstNode::BoundVariable(num) => out.push_str(&num.to_string()),
+		&AstNode::FreeVariable(ch) => out.push(ch),
+		AstNode::Name(name) => out.push_str(name),
+		&AstNode::Integer(num) => out.push_str(&format!("#{}", num)),
+		&AstNode::Builtin(builtin) => out.push_str(builtin.symbol()),
+		AstNode::Foreign(primitive) => out.push_str(&primitive.name),
+		&AstNode::Spanned(_, ref node) |
+		&AstNode::Origin(_, ref node) => key(node, out),
+	}
+}
+
+fn term_key(node: &AstNode) -> String {
+	let mut out = String::new();
+	key(node, &mut out);
+	out
+}
+
+/// Renders every term reachable from the given one by beta steps,
+/// with an edge for each step. Edges are labelled with the number
+/// of the contracted redex in leftmost outermost order, so edges
+/// labelled 1 follow normal order reduction. Normal forms are drawn
+/// with a double border, and terms left unexplored because of
+/// `MAX_TERMS` are dashed.
+pub fn reduction_graph(node: &AstNode) -> String {
+	let mut ids = HashMap::new();
+	let mut terms = vec![reduction::copy_node(node)];
+	ids.insert(term_key(node), 0);
+	let mut lines = Vec::new();
+	let mut explored = 0;
+
+	while explored < terms.len() && explored < MAX_TERMS {
+		let reducts = reduction::single_steps(&terms[explored]);
+		let mut edges: Vec<(usize, Vec<String>)> = Vec::new();
+		for (index, reduct) in reducts.into_iter().enumerate() {
+			let term_key = term_key(&reduct);
+			let target = match ids.get(&term_key) {
+				Some(&id) => id,
+				None => {
+					ids.insert(term_key, terms.len());
+					terms.push(reduct);
+					terms.len() - 1
+				},
+			};
+			// steps reaching the same term share an edge
+			match edges.iter().position(|edge| edge.0 == target) {
+				Some(position) => edges[position].1.push((index + 1).to_string()),
+				None => edges.push((target, vec![(index + 1).to_string()])),
+			}
+		}
+
+		let style = if edges.is_empty() { ", peripheries=2" } else { "" };
+		lines.push(format!("\tt{} [label=\"{}\"{}];",
+		                   explored, escape(&pretty_string(&terms[explored])), style));
+		for (target, labels) in edges {
+			let bold = if labels[0] == "1" { ", style=bold" } else { "" };
+			lines.push(format!("\tt{} -> t{} [label=\"{}\"{}];",
+			                   explored, target, labels.join(", "), bold));
+		}
+		explored += 1;
+	}
+
+	for (id, term) in terms.iter().enumerate().skip(explored) {
+		lines.push(format!("\tt{} [label=\"{}\", style=dashed];",
+		                   id, escape(&pretty_string(term))));
+	}
+
+	format!("digraph reductions {{\n\tnode [shape=box, fontname=\"monospace\"];\n{}\n}}\n",
+	        lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use parser::{self, Syntax};
+
+	fn term(source: &str) -> AstNode {
+		parser::parse(source, Syntax::Lambda).ok().unwrap()
+	}
+
+	#[test]
+	fn identity_graph() {
+		assert_eq!(term_graph(&term("\\x.x")), "digraph term {\n\
+			\tordering=out;\n\
+			\tn0 [label=\"λa\", shape=circle];\n\
+			\tn1 [label=\"a\", shape=plaintext];\n\
+			\tn1 -> n0 [style=dashed, constraint=false];\n\
+			\tn0 -> n1;\n\
+			}\n");
+	}
+
+	#[test]
+	fn steps_to_the_same_term_share_an_edge() {
+		let graph = reduction_graph(&term("(\\x.x) ((\\y.y) z)"));
+		assert_eq!(graph, "digraph reductions {\n\
+			\tnode [shape=box, fontname=\"monospace\"];\n\
+			\tt0 [label=\"(\\\\a.a) ((\\\\a.a) z)\"];\n\
+			\tt0 -> t1 [label=\"1, 2\", style=bold];\n\
+			\tt1 [label=\"(\\\\a.a) z\"];\n\
+			\tt1 -> t2 [label=\"1\", style=bold];\n\
+			\tt2 [label=\"z\", peripheries=2];\n\
+			}\n");
+	}
+
+	#[test]
+	fn large_graphs_are_cut_off() {
+		let omega = term("(\\x.x x x) (\\x.x x x)");
+		let graph = reduction_graph(&omega);
+		assert_eq!(graph.matches("style=dashed").count(), 1);
+		assert!(graph.contains(&format!("\tt{} [label=", MAX_TERMS)));
+		assert!(!graph.contains("peripheries"));
+	}
+}
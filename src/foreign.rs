@@ -0,0 +1,195 @@
+use std::rc::Rc;
+use AstNode;
+use church::{FromChurch, ToChurch};
+use reduction;
+
+/// A function implemented in Rust that lambda terms can call. Once
+/// a primitive is applied to `arity` arguments, they are reduced to
+/// normal form and passed to `call`, and the returned term replaces
+/// the application.
+pub trait Foreign {
+	fn arity(&self) -> usize;
+	fn call(&self, args: &Arguments) -> Result<AstNode, String>;
+}
+
+/// A registered foreign function, as it appears in terms.
+#[derive(Clone)]
+pub struct Primitive {
+	pub name: String,
+	implementation: Rc<dyn Foreign>,
+}
+
+impl Primitive {
+	pub fn new(name: &str, implementation: Rc<dyn Foreign>) -> Primitive {
+		Primitive {
+			name: name.to_string(),
+			implementation,
+		}
+	}
+
+	pub fn arity(&self) -> usize {
+		self.implementation.arity()
+	}
+
+	/// Checks if both are copies of the same registered primitive,
+	/// whatever their names.
+	pub fn is(&self, other: &Primitive) -> bool {
+		Rc::ptr_eq(&self.implementation, &other.implementation)
+	}
+}
+
+/// Arguments of a foreign call, with helpers to decode
+/// the usual Church encodings.
+pub struct Arguments<'a> {
+	nodes: &'a [AstNode],
+}
+
+impl<'a> Arguments<'a> {
+	pub fn len(&self) -> usize {
+		self.nodes.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.nodes.is_empty()
+	}
+
+	/// The argument as it is, in normal form.
+	pub fn term(&self, index: usize) -> &AstNode {
+		&self.nodes[index]
+	}
+
+	/// Decodes the argument with `FromChurch`.
+	pub fn decode<T: FromChurch>(&self, index: usize) -> Result<T, String> {
+		T::from_church(&self.nodes[index])
+			.map_err(|e| format!("argument {}: {}", index + 1, e))
+	}
+
+	pub fn number(&self, index: usize) -> Result<u32, String> {
+		self.decode(index)
+	}
+
+	pub fn boolean(&self, index: usize) -> Result<bool, String> {
+		self.decode(index)
+	}
+
+	/// Decodes a list of character codes built with `CONS` and `NIL`.
+	pub fn string(&self, index: usize) -> Result<String, String> {
+		self.decode(index)
+	}
+}
+
+pub fn number(num: u32) -> AstNode {
+	num.to_church()
+}
+
+pub fn boolean(value: bool) -> AstNode {
+	value.to_church()
+}
+
+/// Encodes the string as a list of character codes.
+pub fn string(value: &str) -> AstNode {
+	value.to_church()
+}
+
+/// Calls the primitive if the normalised function is a primitive
+/// applied to all but its last argument.
+pub fn invoke(function: &AstNode, argument: &AstNode) -> Option<Result<AstNode, String>> {
+	let mut args = vec![argument];
+	let mut head = function;
+	while let AstNode::Application(f, x) = head {
+		args.push(&**x);
+		head = &**f;
+	}
+
+	match head {
+		AstNode::Foreign(primitive) if primitive.arity() == args.len() => {
+			let nodes: Vec<AstNode> = args.iter()
+				.rev()
+				.map(|node| reduction::copy_node(node))
+				.collect();
+			let result = primitive.implementation.call(&Arguments { nodes: &nodes });
+			Some(result.map_err(|e| format!("{}: {}", primitive.name, e)))
+		},
+		_ => None,
+	}
+}
+
+/// Calls a primitive which takes no arguments.
+pub fn invoke_constant(primitive: &Primitive) -> Result<AstNode, String> {
+	let result = primitive.implementation.call(&Arguments { nodes: &[] });
+	result.map_err(|e| format!("{}: {}", primitive.name, e))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use runtime::{numeric_value, Interpreter};
+
+	/// `GCD a b`, with Euclid's algorithm.
+	struct Gcd;
+
+	impl Foreign for Gcd {
+		fn arity(&self) -> usize {
+			2
+		}
+
+		fn call(&self, args: &Arguments) -> Result<AstNode, String> {
+			let (mut a, mut b) = (args.number(0)?, args.number(1)?);
+			while b != 0 {
+				let rest = a % b;
+				a = b;
+				b = rest;
+			}
+			Ok(number(a))
+		}
+	}
+
+	/// `ANSWER`, which takes no arguments.
+	struct Answer;
+
+	impl Foreign for Answer {
+		fn arity(&self) -> usize {
+			0
+		}
+
+		fn call(&self, _: &Arguments) -> Result<AstNode, String> {
+			Ok(number(42))
+		}
+	}
+
+	fn interpreter() -> Interpreter {
+		let mut interpreter = Interpreter::new();
+		interpreter.set_quiet(true);
+		interpreter.load_prelude();
+		interpreter.register("GCD", Rc::new(Gcd));
+		interpreter.register("ANSWER", Rc::new(Answer));
+		interpreter
+	}
+
+	fn evaluate(interpreter: &Interpreter, source: &str) -> Result<AstNode, String> {
+		interpreter.evaluate_source(source, 100000)
+	}
+
+	#[test]
+	fn primitives_are_called_with_normal_forms() {
+		let interpreter = interpreter();
+		let result = evaluate(&interpreter, "GCD (MUL 3 4) (ADD 10 8)").unwrap();
+		assert_eq!(numeric_value(&result), Some(6));
+		let result = evaluate(&interpreter, "SUCC ANSWER").unwrap();
+		assert_eq!(numeric_value(&result), Some(43));
+	}
+
+	#[test]
+	fn partial_applications_wait_for_arguments() {
+		let interpreter = interpreter();
+		let result = evaluate(&interpreter, "(\\f.f 12) (GCD 8)").unwrap();
+		assert_eq!(numeric_value(&result), Some(4));
+	}
+
+	#[test]
+	fn decoding_errors_name_the_primitive() {
+		let interpreter = interpreter();
+		let error = evaluate(&interpreter, "GCD TRUE 2").err().unwrap();
+		assert!(error.starts_with("GCD: argument 1: "), "{}", error);
+	}
+}
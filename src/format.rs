@@ -0,0 +1,526 @@
+//! Stable interchange formats for terms and named environments.
+//!
+//! Both formats store terms exactly as the interpreter holds them:
+//! bound variables keep their de Bruijn indices, and references to
+//! named definitions are kept as names rather than expanded, so
+//! writing a term and reading it back gives the same term.
+//!
+//! # JSON
+//!
+//! A term is an object with a `"type"` member:
+//!
+//! | type            | members                                |
+//! |-----------------|----------------------------------------|
+//! | `"free"`        | `"name"`: one-character string         |
+//! | `"bound"`       | `"index"`: de Bruijn index, 0 = nearest binder |
+//! | `"function"`    | `"body"`: term                         |
+//! | `"application"` | `"function"`: term, `"argument"`: term |
+//! | `"name"`        | `"name"`: name of a definition or operator |
+//! | `"integer"`     | `"value"`: native integer              |
+//! | `"builtin"`     | `"symbol"`: one of `+ * - == <=`       |
+//!
+//! so `\x.x y` is
+//! `{"type":"function","body":{"type":"application","function":{"type":"bound","index":0},"argument":{"type":"free","name":"y"}}}`.
+//!
+//! An environment is
+//! `{"version":1,"definitions":{"NAME":term,...}}`.
+//!
+//! # S-expressions
+//!
+//! Terms are written as `(free y)`, `(bound 0)`, `(function BODY)`,
+//! `(application FUNCTION ARGUMENT)`, `(name ADD)`, `(integer 3)` and
+//! `(builtin +)`. An environment is a sequence of `(define NAME TERM)`.
+//!
+//! Foreign functions are registered by the host program, so terms
+//! containing them can't be written in either format.
+
+use std::collections::BTreeMap;
+use AstNode;
+use json::Json;
+use native::Builtin;
+use parser;
+
+/// Version of the environment format, increased on incompatible changes.
+pub const VERSION: u32 = 1;
+
+/// Named definitions, as bound with `NAME := expr`. With the `serde`
+/// feature enabled, this and `AstNode` implement `Serialize` and
+/// `Deserialize` following the JSON schema above.
+pub struct Environment {
+	pub definitions: BTreeMap<String, AstNode>,
+}
+
+fn foreign_error(name: &str) -> String {
+	format!("foreign function {} can't be serialized", name)
+}
+
+pub fn to_json(node: &AstNode) -> Result<Json, String> {
+	Ok(match node {
+		&AstNode::FreeVariable(ch) => Json::object(vec![
+			("type", Json::string("free")),
+			("name", Json::String(ch.to_string())),
+		]),
+		&AstNode::BoundVariable(index) => Json::object(vec![
+			("type", Json::string("bound")),
+			("index", Json::Number(index as f64)),
+		]),
+		AstNode::Function(body) => Json::object(vec![
+			("type", Json::string("function")),
+			("body", to_json(body)?),
+		]),
+		AstNode::Application(a, b) => Json::object(vec![
+			("type", Json::string("application")),
+			("function", to_json(a)?),
+			("argument", to_json(b)?),
+		]),
+		AstNode::Name(name) => Json::object(vec![
+			("type", Json::string("name")),
+			("name", Json::string(name)),
+		]),
+		&AstNode::Integer(num) => Json::object(vec![
+			("type", Json::string("integer")),
+			("value", Json::Number(num as f64)),
+		]),
+		&AstNode::Builtin(builtin) => Json::object(vec![
+			("type", Json::string("builtin")),
+			("symbol", Json::string(builtin.symbol())),
+		]),
+		AstNode::Foreign(primitive) => return Err(foreign_error(&primitive.name)),
+		&AstNode::Spanned(_, ref node) |
+		&AstNode::Origin(_, ref node) => return to_json(node),
+	})
+}
+
+fn member<'a>(json: &'a Json, key: &str) -> Result<&'a Json, String> {
+	json.get(key).ok_or_else(|| format!("missing member: {}", key))
+}
+
+fn string_member<'a>(json: &'a Json, key: &str) -> Result<&'a str, String> {
+	member(json, key)?.as_str().ok_or_else(|| format!("{} must be a string", key))
+}
+
+fn number_member(json: &Json, key: &str) -> Result<u32, String> {
+	member(json, key)?.as_u32().ok_or_else(|| format!("{} must be a non-negative integer", key))
+}
+
+fn free_variable(name: &str) -> Result<AstNode, String> {
+	let mut chars = name.chars();
+	match (chars.next(), chars.next()) {
+		(Some(ch), None) => Ok(AstNode::FreeVariable(ch)),
+		_ => Err(format!("invalid free variable: {}", name)),
+	}
+}
+
+/// Bound variable with the index, which must refer to one of the
+/// `depth` functions around it.
+fn bound_variable(index: u32, depth: u32) -> Result<AstNode, String> {
+	if index < depth {
+		Ok(AstNode::BoundVariable(index))
+	} else {
+		Err(format!("bound index {} doesn't refer to an enclosing function", index))
+	}
+}
+
+fn check_definition_name(name: &str) -> Result<(), String> {
+	parser::check_name(name).map_err(|message| format!("{}: {}", message, name))
+}
+
+/// Reference to the named definition, or to an operator, which is
+/// kept as a name until the interpreter picks between the native
+/// function and the prelude definition.
+fn name(name: &str) -> Result<AstNode, String> {
+	if Builtin::from_symbol(name).is_none() {
+		check_definition_name(name)?;
+	}
+	Ok(AstNode::Name(name.to_string()))
+}
+
+fn builtin(symbol: &str) -> Result<AstNode, String> {
+	Builtin::from_symbol(symbol)
+		.map(AstNode::Builtin)
+		.ok_or_else(|| format!("unknown builtin: {}", symbol))
+}
+
+/// Reads a closed term, see the module documentation.
+pub fn from_json(json: &Json) -> Result<AstNode, String> {
+	term_from_json(json, 0)
+}
+
+/// `depth` is the number of functions around the term.
+fn term_from_json(json: &Json, depth: u32) -> Result<AstNode, String> {
+	match string_member(json, "type")? {
+		"free" => free_variable(string_member(json, "name")?),
+		"bound" => bound_variable(number_member(json, "index")?, depth),
+		"function" => Ok(AstNode::Function(
+			Box::new(term_from_json(member(json, "body")?, depth + 1)?))),
+		"application" => Ok(AstNode::Application(
+			Box::new(term_from_json(member(json, "function")?, depth)?),
+			Box::new(term_from_json(member(json, "argument")?, depth)?))),
+		"name" => name(string_member(json, "name")?),
+		"integer" => Ok(AstNode::Integer(number_member(json, "value")?)),
+		"builtin" => builtin(string_member(json, "symbol")?),
+		other => Err(format!("unknown term type: {}", other)),
+	}
+}
+
+pub fn environment_to_json(env: &Environment) -> Result<Json, String> {
+	let mut definitions = Vec::new();
+	for (name, node) in env.definitions.iter() {
+		let term = to_json(node).map_err(|e| format!("in {}: {}", name, e))?;
+		definitions.push((name.clone(), term));
+	}
+	Ok(Json::object(vec![
+		("version", Json::Number(VERSION as f64)),
+		("definitions", Json::Object(definitions)),
+	]))
+}
+
+pub fn environment_from_json(json: &Json) -> Result<Environment, String> {
+	let version = number_member(json, "version")?;
+	if version != VERSION {
+		return Err(format!("unsupported version: {}", version));
+	}
+	let mut definitions = BTreeMap::new();
+	match member(json, "definitions")? {
+		Json::Object(members) => {
+			for (name, term) in members.iter() {
+				check_definition_name(name)?;
+				let node = from_json(term).map_err(|e| format!("in {}: {}", name, e))?;
+				definitions.insert(name.clone(), node);
+			}
+		},
+		_ => return Err("definitions must be an object".to_string()),
+	}
+	Ok(Environment { definitions })
+}
+
+fn write_sexp(node: &AstNode, out: &mut String) -> Result<(), String> {
+	match node {
+		&AstNode::FreeVariable(ch) => out.push_str(&format!("(free {})", ch)),
+		&AstNode::BoundVariable(index) => out.push_str(&format!("(bound {})", index)),
+		AstNode::Function(body) => {
+			out.push_str("(function ");
+			write_sexp(body, out)?;
+			out.push(')');
+		},
+		AstNode::Application(a, b) => {
+			out.push_str("(application ");
+			write_sexp(a, out)?;
+			out.push(' ');
+			write_sexp(b, out)?;
+			out.push(')');
+		},
+		AstNode::Name(name) => out.push_str(&format!("(name {})", name)),
+		&AstNode::Integer(num) => out.push_str(&format!("(integer {})", num)),
+		&AstNode::Builtin(builtin) => out.push_str(&format!("(builtin {})", builtin.symbol())),
+		AstNode::Foreign(primitive) => return Err(foreign_error(&primitive.name)),
+		&AstNode::Spanned(_, ref node) |
+		&AstNode::Origin(_, ref node) => return write_sexp(node, out),
+	}
+	Ok(())
+}
+
+pub fn to_sexp(node: &AstNode) -> Result<String, String> {
+	let mut out = String::new();
+	write_sexp(node, &mut out)?;
+	Ok(out)
+}
+
+pub fn environment_to_sexp(env: &Environment) -> Result<String, String> {
+	let mut out = String::new();
+	for (name, node) in env.definitions.iter() {
+		out.push_str(&format!("(define {} ", name));
+		write_sexp(node, &mut out).map_err(|e| format!("in {}: {}", name, e))?;
+		out.push_str(")\n");
+	}
+	Ok(out)
+}
+
+enum Sexp {
+	Atom(String),
+	List(Vec<Sexp>),
+}
+
+fn tokenize(source: &str) -> Vec<String> {
+	let mut tokens = Vec::new();
+	let mut current = String::new();
+	for ch in source.chars() {
+		if ch == '(' || ch == ')' || ch.is_whitespace() {
+			if !current.is_empty() {
+				tokens.push(current);
+				current = String::new();
+			}
+			if !ch.is_whitespace() {
+				tokens.push(ch.to_string());
+			}
+		} else {
+			current.push(ch);
+		}
+	}
+	if !current.is_empty() {
+		tokens.push(current);
+	}
+	tokens
+}
+
+fn read_sexp(tokens: &[String], position: &mut usize) -> Result<Sexp, String> {
+	let token = match tokens.get(*position) {
+		Some(token) => token,
+		None => return Err("unexpected end of input".to_string()),
+	};
+	*position += 1;
+	match &**token {
+		"(" => {
+			let mut items = Vec::new();
+			loop {
+				match tokens.get(*position).map(|token| &**token) {
+					Some(")") => {
+						*position += 1;
+						return Ok(Sexp::List(items));
+					},
+					Some(..) => items.push(read_sexp(tokens, position)?),
+					None => return Err("missing )".to_string()),
+				}
+			}
+		},
+		")" => Err("unexpected )".to_string()),
+		atom => Ok(Sexp::Atom(atom.to_string())),
+	}
+}
+
+fn read_all(source: &str) -> Result<Vec<Sexp>, String> {
+	let tokens = tokenize(source);
+	let mut position = 0;
+	let mut result = Vec::new();
+	while position < tokens.len() {
+		result.push(read_sexp(&tokens, &mut position)?);
+	}
+	Ok(result)
+}
+
+fn atom(sexp: &Sexp) -> Result<&str, String> {
+	match sexp {
+		Sexp::Atom(atom) => Ok(atom),
+		&Sexp::List(..) => Err("expected an atom".to_string()),
+	}
+}
+
+fn number_atom(sexp: &Sexp) -> Result<u32, String> {
+	let atom = atom(sexp)?;
+	atom.parse().map_err(|_| format!("expected a non-negative integer, got {}", atom))
+}
+
+/// `depth` is the number of functions around the term.
+fn term_from_sexp(sexp: &Sexp, depth: u32) -> Result<AstNode, String> {
+	let items = match sexp {
+		Sexp::List(items) if !items.is_empty() => items,
+		_ => return Err("expected a term".to_string()),
+	};
+	let kind = atom(&items[0])?;
+	match (kind, &items[1..]) {
+		("free", [name]) => free_variable(atom(name)?),
+		("bound", [index]) => bound_variable(number_atom(index)?, depth),
+		("function", [body]) =>
+			Ok(AstNode::Function(Box::new(term_from_sexp(body, depth + 1)?))),
+		("application", [a, b]) => Ok(AstNode::Application(
+			Box::new(term_from_sexp(a, depth)?),
+			Box::new(term_from_sexp(b, depth)?))),
+		("name", [atom_name]) => name(atom(atom_name)?),
+		("integer", [value]) => Ok(AstNode::Integer(number_atom(value)?)),
+		("builtin", [symbol]) => builtin(atom(symbol)?),
+		_ => Err(format!("invalid {} term", kind)),
+	}
+}
+
+pub fn from_sexp(source: &str) -> Result<AstNode, String> {
+	let mut items = read_all(source)?;
+	match items.len() {
+		1 => term_from_sexp(&items.pop().unwrap(), 0),
+		0 => Err("expected a term".to_string()),
+		_ => Err("expected a single term".to_string()),
+	}
+}
+
+pub fn environment_from_sexp(source: &str) -> Result<Environment, String> {
+	let mut definitions = BTreeMap::new();
+	for item in read_all(source)?.iter() {
+		match item {
+			Sexp::List(items) if items.len() == 3 && atom(&items[0]).ok() == Some("define") => {
+				let name = atom(&items[1])?;
+				check_definition_name(name)?;
+				let node = term_from_sexp(&items[2], 0).map_err(|e| format!("in {}: {}", name, e))?;
+				definitions.insert(name.to_string(), node);
+			},
+			_ => return Err("expected (define NAME TERM)".to_string()),
+		}
+	}
+	Ok(Environment { definitions })
+}
+
+#[cfg(feature = "serde")]
+mod serde_impls {
+	use serde::{Deserialize, Deserializer, Serialize, Serializer};
+	use serde::de::Error as DeError;
+	use serde::ser::{Error as SerError, SerializeStruct};
+	use std::collections::BTreeMap;
+	use AstNode;
+	use native::Builtin;
+	use super::Environment;
+
+	/// Mirrors the JSON schema above, so that serde's output in JSON
+	/// is the same as `to_json`.
+	#[derive(Serialize, Deserialize)]
+	#[serde(tag = "type", rename_all = "snake_case")]
+	enum Wire {
+		Free { name: char },
+		Bound { index: u32 },
+		Function { body: Box<Wire> },
+		Application { function: Box<Wire>, argument: Box<Wire> },
+		Name { name: String },
+		Integer { value: u32 },
+		Builtin { symbol: String },
+	}
+
+	fn to_wire(node: &AstNode) -> Result<Wire, String> {
+		Ok(match node {
+			&AstNode::FreeVariable(ch) => Wire::Free { name: ch },
+			&AstNode::BoundVariable(index) => Wire::Bound { index },
+			AstNode::Function(body) => Wire::Function { body: Box::new(to_wire(body)?) },
+			AstNode::Application(a, b) => Wire::Application {
+				function: Box::new(to_wire(a)?),
+				argument: Box::new(to_wire(b)?),
+			},
+			AstNode::Name(name) => Wire::Name { name: name.clone() },
+			&AstNode::Integer(num) => Wire::Integer { value: num },
+			&AstNode::Builtin(builtin) => Wire::Builtin { symbol: builtin.symbol().to_string() },
+			AstNode::Foreign(primitive) => return Err(super::foreign_error(&primitive.name)),
+			&AstNode::Spanned(_, ref node) |
+			&AstNode::Origin(_, ref node) => return to_wire(node),
+		})
+	}
+
+	/// `depth` is the number of functions around the term.
+	fn from_wire(wire: Wire, depth: u32) -> Result<AstNode, String> {
+		Ok(match wire {
+			Wire::Free { name } => AstNode::FreeVariable(name),
+			Wire::Bound { index } => super::bound_variable(index, depth)?,
+			Wire::Function { body } => AstNode::Function(Box::new(from_wire(*body, depth + 1)?)),
+			Wire::Application { function, argument } => AstNode::Application(
+				Box::new(from_wire(*function, depth)?),
+				Box::new(from_wire(*argument, depth)?)),
+			Wire::Name { name } => super::name(&name)?,
+			Wire::Integer { value } => AstNode::Integer(value),
+			Wire::Builtin { symbol } => Builtin::from_symbol(&symbol)
+				.map(AstNode::Builtin)
+				.ok_or_else(|| format!("unknown builtin: {}", symbol))?,
+		})
+	}
+
+	impl Serialize for AstNode {
+		fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+			to_wire(self).map_err(S::Error::custom)?.serialize(serializer)
+		}
+	}
+
+	impl<'de> Deserialize<'de> for AstNode {
+		fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<AstNode, D::Error> {
+			let wire = Wire::deserialize(deserializer)?;
+			from_wire(wire, 0).map_err(D::Error::custom)
+		}
+	}
+
+	#[derive(Deserialize)]
+	struct EnvironmentWire {
+		version: u32,
+		definitions: BTreeMap<String, AstNode>,
+	}
+
+	impl Serialize for Environment {
+		fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+			let mut state = serializer.serialize_struct("Environment", 2)?;
+			state.serialize_field("version", &super::VERSION)?;
+			state.serialize_field("definitions", &self.definitions)?;
+			state.end()
+		}
+	}
+
+	impl<'de> Deserialize<'de> for Environment {
+		fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Environment, D::Error> {
+			let wire = EnvironmentWire::deserialize(deserializer)?;
+			if wire.version != super::VERSION {
+				return Err(D::Error::custom(format!("unsupported version: {}", wire.version)));
+			}
+			for name in wire.definitions.keys() {
+				super::check_definition_name(name).map_err(D::Error::custom)?;
+			}
+			Ok(Environment { definitions: wire.definitions })
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use json;
+	use parser::{self, Syntax};
+
+	fn term(source: &str) -> AstNode {
+		parser::parse(source, Syntax::Lambda).ok().unwrap()
+	}
+
+	#[test]
+	fn terms_survive_both_formats() {
+		let node = term("\\x.\\y.ADD x (y 3) z");
+		let sexp = to_sexp(&node).unwrap();
+		assert_eq!(sexp, "(function (function (application (application (application (name ADD) (bound 1)) \
+			(application (bound 0) (integer 3))) (free z))))");
+		assert_eq!(to_sexp(&from_sexp(&sexp).unwrap()).unwrap(), sexp);
+		let json = to_json(&node).unwrap().to_string();
+		assert_eq!(to_sexp(&from_json(&json::parse(&json).unwrap()).unwrap()).unwrap(), sexp);
+	}
+
+	#[test]
+	fn environments_survive_both_formats() {
+		let mut definitions = BTreeMap::new();
+		definitions.insert("ID".to_string(), term("\\x.x"));
+		definitions.insert("TWICE".to_string(), term("\\f.\\x.f (f x)"));
+		let env = Environment { definitions };
+		let sexp = environment_to_sexp(&env).unwrap();
+		assert_eq!(environment_to_sexp(&environment_from_sexp(&sexp).unwrap()).unwrap(), sexp);
+		let json = environment_to_json(&env).unwrap();
+		assert_eq!(environment_to_sexp(&environment_from_json(&json).unwrap()).unwrap(), sexp);
+	}
+
+	#[test]
+	fn operators_survive_both_formats() {
+		let mut definitions = BTreeMap::new();
+		definitions.insert("PLUS".to_string(), term("+ 1"));
+		definitions.insert("SMALL".to_string(), term("\\x.<= x 3"));
+		let env = Environment { definitions };
+		let sexp = environment_to_sexp(&env).unwrap();
+		assert_eq!(sexp, "(define PLUS (application (name +) (integer 1)))\n\
+			(define SMALL (function (application (application (name <=) (bound 0)) (integer 3))))\n");
+		assert_eq!(environment_to_sexp(&environment_from_sexp(&sexp).unwrap()).unwrap(), sexp);
+		let json = environment_to_json(&env).unwrap();
+		assert_eq!(environment_to_sexp(&environment_from_json(&json).unwrap()).unwrap(), sexp);
+	}
+
+	#[test]
+	fn bound_indices_must_refer_to_functions() {
+		assert!(from_sexp("(bound 0)").is_err());
+		assert!(from_sexp("(function (bound 1))").is_err());
+		assert!(from_sexp("(function (function (bound 1)))").is_ok());
+		let json = json::parse("{\"type\":\"function\",\"body\":{\"type\":\"bound\",\"index\":1}}").unwrap();
+		assert!(from_json(&json).is_err());
+		assert!(environment_from_sexp("(define FOO (bound 3))").is_err());
+	}
+
+	#[test]
+	fn names_must_be_valid() {
+		assert!(from_sexp("(name foo)").is_err());
+		assert!(environment_from_sexp("(define foo (free x))").is_err());
+		assert!(environment_from_sexp("(define 1X (free x))").is_err());
+		let json = json::parse("{\"version\":1,\"definitions\":{\"bad\":{\"type\":\"free\",\"name\":\"x\"}}}").unwrap();
+		assert!(environment_from_json(&json).is_err());
+	}
+}
@@ -0,0 +1,272 @@
+use std::rc::Rc;
+use diagnostic::{Diagnostic, Source};
+use check::{self, Check};
+use parser::{self, Cst, Trivia};
+use property;
+
+/// Indentation of lines continuing a statement, unless
+/// they are already indented.
+const CONTINUATION: &str = "\n  ";
+
+/// Writes the whitespace before a token: comments and line breaks are
+/// kept, anything else becomes the given separator.
+fn write_trivia(whitespace: &str, separator: &str, out: &mut String) {
+	let mut line_start = false;
+	let mut after_comment = false;
+	for item in parser::trivia(whitespace) {
+		match item {
+			Trivia::Newline(indent) => {
+				if !line_start {
+					out.push_str(CONTINUATION);
+				}
+				if !indent.is_empty() {
+					// the user's indentation replaces the default one
+					let trimmed = out.trim_end_matches(' ').len();
+					out.truncate(trimmed);
+					out.push_str(&indent);
+				}
+				line_start = true;
+				after_comment = false;
+			},
+			Trivia::Comment(text) => {
+				if !line_start && !out.is_empty() && !out.ends_with(' ') {
+					out.push(' ');
+				}
+				out.push_str(&text);
+				// line comments run to the end of the line
+				line_start = text.starts_with("--") || text.starts_with('#');
+				if line_start {
+					out.push_str(CONTINUATION);
+				}
+				after_comment = !line_start;
+			},
+		}
+	}
+	if after_comment {
+		out.push(' ');
+	} else if !line_start {
+		out.push_str(separator);
+	}
+}
+
+fn format_cst(cst: &Cst, separator: &str, out: &mut String) {
+	match cst {
+		&Cst::Variable(ref token) |
+		&Cst::Integer(ref token, _) |
+		&Cst::Name(ref token) => {
+			write_trivia(&token.leading, separator, out);
+			out.push_str(&token.text);
+		},
+		Cst::Parenthesized(open, inner, close) => {
+			write_trivia(&open.leading, separator, out);
+			out.push('(');
+			format_cst(inner, "", out);
+			write_trivia(&close.leading, "", out);
+			out.push(')');
+		},
+		Cst::Function(lambda, params, dot, body) => {
+			write_trivia(&lambda.leading, separator, out);
+			out.push('\\');
+			for param in params.iter() {
+				write_trivia(&param.leading, "", out);
+				out.push_str(&param.text);
+			}
+			write_trivia(&dot.leading, "", out);
+			out.push('.');
+			format_cst(body, "", out);
+		},
+		Cst::Application(items) => {
+			for (index, item) in items.iter().enumerate() {
+				format_cst(item, if index == 0 { separator } else { " " }, out);
+			}
+		},
+	}
+}
+
+/// Writes the term in canonical form: binder names, parentheses,
+/// comments and line breaks are kept as written, and otherwise
+/// terms are separated by single spaces.
+pub fn format_term(tree: &parser::SyntaxTree) -> String {
+	let mut out = String::new();
+	format_cst(&tree.term, "", &mut out);
+	write_trivia(&tree.trailing, "", &mut out);
+	out
+}
+
+enum Kind {
+	/// Comments, or a command, kept as written.
+	Verbatim(String),
+	Definition(String, String),
+	Expression(String),
+}
+
+/// Reports syntax errors of a term that starts at `offset`
+/// in the statement.
+fn term_errors(errors: Vec<parser::ParseError>, offset: usize, source: &Rc<Source>) -> Vec<Diagnostic> {
+	errors.into_iter().map(|mut error| {
+		error.position += offset;
+		error.related = error.related.map(|related| related + offset);
+		error.diagnostic(source)
+	}).collect()
+}
+
+fn format_statement(file: &str, statement: &parser::Statement) -> Result<Kind, Vec<Diagnostic>> {
+	let text = &statement.text;
+	if parser::is_blank(text) || text.trim().starts_with(':') {
+		return Ok(Kind::Verbatim(text.trim().to_string()));
+	}
+
+	let source = Source::in_file(file, statement.line, text);
+	if property::is_law(text) {
+		// the variables are written canonically, and the body as a term
+		let law = property::parse_law(&source).map_err(|diagnostic| vec![diagnostic])?;
+		let body: String = text.chars().skip(law.body).collect();
+		return match parser::parse_tree_all(&body) {
+			Ok(tree) => Ok(Kind::Expression(format!("{} {}", law, format_term(&tree).trim_start()))),
+			Err(errors) => Err(term_errors(errors, law.body, &source)),
+		};
+	}
+
+	if let Some(check) = check::parse_check(&source) {
+		let check = check.map_err(|diagnostic| vec![diagnostic])?;
+		let mut terms = Vec::new();
+		let mut errors = Vec::new();
+		for (start, end) in check::term_ranges(&check, &source) {
+			let term: String = text.chars().skip(start).take(end - start).collect();
+			match parser::parse_tree_all(&term) {
+				Ok(tree) => terms.push(format_term(&tree).trim().to_string()),
+				Err(term_errors) => errors.extend(self::term_errors(term_errors, start, &source)),
+			}
+		}
+		if !errors.is_empty() {
+			return Err(errors);
+		}
+		return Ok(Kind::Expression(match check {
+			Check::Assert(..) => format!("assert {}", terms[0]),
+			Check::Expect(..) => format!("expect {} == {}", terms[0], terms[1]),
+		}));
+	}
+
+	let (name, expr, offset) = match text.find(":=") {
+		Some(index) => (Some(text[..index].trim()), &text[index + 2..], text[..index + 2].chars().count()),
+		None => (None, &text[..], 0),
+	};
+	match parser::parse_tree_all(expr) {
+		Ok(tree) => {
+			let term = format_term(&tree);
+			Ok(match name {
+				Some(name) => Kind::Definition(name.to_string(), term),
+				None => Kind::Expression(term),
+			})
+		},
+		Err(errors) => Err(term_errors(errors, offset, &source)),
+	}
+}
+
+/// Formats a script of definitions and expressions: terms are written
+/// canonically, `:=` is aligned within each block of consecutive
+/// definitions, statements are separated by at most one blank line,
+/// and the file ends with a newline. Every syntax error in the file
+/// is reported, naming the given file.
+pub fn format_source(file: &str, source: &str) -> Result<String, Vec<Diagnostic>> {
+	let statements = parser::split_statements(source);
+	let mut kinds = Vec::new();
+	let mut errors = Vec::new();
+	for statement in statements.iter() {
+		match format_statement(file, statement) {
+			Ok(kind) => kinds.push(kind),
+			Err(diagnostics) => errors.extend(diagnostics),
+		}
+	}
+	if !errors.is_empty() {
+		return Err(errors);
+	}
+
+	let mut out = String::new();
+	let mut index = 0;
+	while index < kinds.len() {
+		// definitions up to a blank line or another statement form a block
+		let mut block_end = index;
+		while block_end < kinds.len() {
+			match kinds[block_end] {
+				Kind::Definition(..) if block_end == index || !statements[block_end].after_blank_line =>
+					block_end += 1,
+				_ => break,
+			}
+		}
+		let width = kinds[index..block_end].iter()
+			.map(|kind| match kind { Kind::Definition(name, _) => name.chars().count(), _ => 0 })
+			.max()
+			.unwrap_or(0);
+
+		let end = if block_end == index { index + 1 } else { block_end };
+		for position in index..end {
+			if statements[position].after_blank_line {
+				out.push('\n');
+			}
+			let text = match kinds[position] {
+				Kind::Definition(ref name, ref term) =>
+					format!("{:width$} := {}", name, term, width = width),
+				Kind::Verbatim(ref text) | Kind::Expression(ref text) =>
+					text.clone(),
+			};
+			for line in text.trim_end().lines() {
+				out.push_str(line.trim_end());
+				out.push('\n');
+			}
+		}
+		index = end;
+	}
+	Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const MESSY: &str = "\
+ID:=\\x.x   -- identity
+TWO   :=  \\f.\\x. f (f  x)
+
+
+
+-- numbers
+ADD  TWO  ( ID TWO )  
+:eq   TWO 2";
+
+	const FORMATTED: &str = "\
+ID  := \\x.x -- identity
+TWO := \\f.\\x.f (f x)
+
+-- numbers
+ADD TWO (ID TWO)
+:eq   TWO 2
+";
+
+	fn format(source: &str) -> String {
+		format_source("script.lam", source).ok().unwrap()
+	}
+
+	#[test]
+	fn scripts_are_formatted() {
+		assert_eq!(format(MESSY), FORMATTED);
+	}
+
+	#[test]
+	fn formatting_is_idempotent() {
+		assert_eq!(format(FORMATTED), FORMATTED);
+		for script in [MESSY, "A := B\n\n\n\nB:=(A)", "-- only a comment", ""].iter() {
+			let once = format(script);
+			assert_eq!(format(&once), once);
+		}
+	}
+
+	#[test]
+	fn every_syntax_error_is_reported() {
+		let errors = format_source("bad.lam", "A := \\x.\nB := )\nC := B").err().unwrap();
+		let locations: Vec<String> = errors.iter()
+			.map(|error| error.span.as_ref().unwrap().location())
+			.collect();
+		assert_eq!(locations, ["bad.lam:1:9", "bad.lam:2:6", "bad.lam:2:6"]);
+	}
+}
@@ -0,0 +1,160 @@
+use std::fs::{self, File};
+use std::io;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use output;
+use runtime::Interpreter;
+
+/// Lines of a line-by-line diff, from the longest common subsequence
+/// of the lines. Removed lines start with `-`, added ones with `+`.
+pub fn diff(expected: &str, actual: &str) -> Vec<String> {
+	let old: Vec<&str> = expected.lines().collect();
+	let new: Vec<&str> = actual.lines().collect();
+	// common[i][j] is the length of the common subsequence of old[i..] and new[j..]
+	let mut common = vec![vec![0; new.len() + 1]; old.len() + 1];
+	for i in (0..old.len()).rev() {
+		for j in (0..new.len()).rev() {
+			common[i][j] = if old[i] == new[j] {
+				common[i + 1][j + 1] + 1
+			} else {
+				common[i + 1][j].max(common[i][j + 1])
+			};
+		}
+	}
+
+	let mut lines = Vec::new();
+	let (mut i, mut j) = (0, 0);
+	while i < old.len() || j < new.len() {
+		if i < old.len() && j < new.len() && old[i] == new[j] {
+			lines.push(format!(" {}", old[i]));
+			i += 1;
+			j += 1;
+		} else if j == new.len() || (i < old.len() && common[i + 1][j] >= common[i][j + 1]) {
+			lines.push(format!("-{}", old[i]));
+			i += 1;
+		} else {
+			lines.push(format!("+{}", new[j]));
+			j += 1;
+		}
+	}
+	lines
+}
+
+/// Unchanged lines shown around changed ones.
+const CONTEXT: usize = 2;
+
+/// Prints the changed lines of the diff with some context.
+fn print_changes(lines: &[String]) {
+	let changed = |index: usize| !lines[index].starts_with(' ');
+	let mut skipped = false;
+	for index in 0..lines.len() {
+		let from = index.saturating_sub(CONTEXT);
+		let to = (index + CONTEXT + 1).min(lines.len());
+		if (from..to).any(&changed) {
+			if skipped {
+				println!("    ...");
+				skipped = false;
+			}
+			println!("    {}", lines[index]);
+		} else {
+			skipped = true;
+		}
+	}
+}
+
+/// Runs the script with the interpreter, from the script's directory
+/// so that file names in the output don't depend on where the tests
+/// are run from.
+fn run_script(program: &Path, path: &Path) -> Result<String, String> {
+	let directory = match path.parent() {
+		Some(directory) if !directory.as_os_str().is_empty() => directory,
+		_ => Path::new("."),
+	};
+	let name = path.file_name().expect("script without a file name");
+	let output = Command::new(program)
+		.arg("--run")
+		.arg(name)
+		.current_dir(directory)
+		.stdin(Stdio::null())
+		.output()
+		.map_err(|e| format!("failed to run {}: {}", path.display(), e))?;
+	Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Runs the script like `run_script` does, but with an interpreter in
+/// this process, which is much faster than starting one per script.
+fn run_script_in_process(path: &Path) -> Result<String, String> {
+	let mut contents = String::new();
+	File::open(path).and_then(|mut f| f.read_to_string(&mut contents))
+		.map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+	let name = path.file_name().expect("script without a file name").to_string_lossy();
+	let ((), output) = output::capture(|| {
+		let mut interpreter = Interpreter::new();
+		interpreter.set_quiet(true);
+		interpreter.load_prelude();
+		interpreter.load_source(&name, &contents);
+	});
+	Ok(output)
+}
+
+/// Adds the `.lam` scripts in the directory and its subdirectories.
+fn find_scripts(directory: &Path, scripts: &mut Vec<PathBuf>) -> io::Result<()> {
+	for entry in fs::read_dir(directory)? {
+		let path = entry?.path();
+		if path.is_dir() {
+			find_scripts(&path, scripts)?;
+		} else if path.extension().and_then(|e| e.to_str()) == Some("lam") {
+			scripts.push(path);
+		}
+	}
+	Ok(())
+}
+
+/// Runs every `.lam` script under the directory and compares its output
+/// with the `.expected` file next to it, printing the differences.
+/// `program` is the interpreter to run the scripts with.
+pub fn run_tests(program: &Path, directory: &str) -> bool {
+	run_all(directory, |script| run_script(program, script))
+}
+
+/// Like `run_tests`, with the interpreter of this library.
+pub fn run_tests_in_process(directory: &str) -> bool {
+	run_all(directory, run_script_in_process)
+}
+
+fn run_all<F>(directory: &str, run: F) -> bool
+	where F: Fn(&Path) -> Result<String, String>
+{
+	let mut scripts = Vec::new();
+	if let Err(e) = find_scripts(Path::new(directory), &mut scripts) {
+		println!("[Error] failed to read {}: {}", directory, e);
+		return false;
+	}
+	scripts.sort();
+
+	let mut failed = 0;
+	for script in scripts.iter() {
+		let expected_path = script.with_extension("expected");
+		let mut expected = String::new();
+		if let Err(e) = File::open(&expected_path).and_then(|mut f| f.read_to_string(&mut expected)) {
+			println!("FAIL {}: failed to read {}: {}", script.display(), expected_path.display(), e);
+			failed += 1;
+			continue;
+		}
+		match run(script) {
+			Ok(ref actual) if *actual == expected => println!("ok   {}", script.display()),
+			Ok(actual) => {
+				println!("FAIL {}", script.display());
+				print_changes(&diff(&expected, &actual));
+				failed += 1;
+			},
+			Err(message) => {
+				println!("FAIL {}: {}", script.display(), message);
+				failed += 1;
+			},
+		}
+	}
+	println!("{} passed, {} failed", scripts.len() - failed, failed);
+	failed == 0
+}
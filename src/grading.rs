@@ -0,0 +1,304 @@
+use std::fmt;
+use std::rc::Rc;
+use AstNode;
+use diagnostic::{Diagnostic, Source, Span};
+use json::Json;
+use parser;
+use pretty_string;
+use reduction;
+use runtime::{boolean_value_eta, numeric_value_eta, Interpreter};
+
+/// Number of beta reductions each side of a test may take.
+const FUEL: usize = 10000;
+
+/// A problem for students, read from a script like this:
+///
+/// ```text
+/// exercise ISEVEN
+/// prompt Define ISEVEN, which is TRUE for even numbers
+///   and FALSE for odd ones.
+/// reference ISEVEN := \n.n NOT TRUE
+/// test 0
+/// test 7
+/// ```
+///
+/// References are definitions that students don't see, one of them
+/// for the name of the exercise. Each test gives the arguments the
+/// student's definition is applied to, and it passes if the result
+/// is the same as the reference's.
+pub struct Exercise {
+	pub name: String,
+	pub prompt: String,
+	/// Definitions, together with where they start in the statement.
+	references: Vec<(Rc<Source>, usize)>,
+	pub tests: Vec<String>,
+}
+
+/// Parses an exercise, see `Exercise`, and checks its references.
+pub fn parse_exercise(file: &str, contents: &str) -> Result<Exercise, Vec<Diagnostic>> {
+	let mut name = None;
+	let mut prompt = Vec::new();
+	let mut references = Vec::new();
+	let mut tests = Vec::new();
+	let mut errors = Vec::new();
+	for statement in parser::split_statements(contents) {
+		let source = Source::in_file(file, statement.line, &statement.text);
+		if parser::is_blank(&source.text) {
+			continue;
+		}
+		let keyword = source.text.split_whitespace().next().unwrap_or("");
+		let start = match parser::statement_keyword(&source.text, keyword) {
+			Some(start) => start,
+			None => {
+				let span = Span::new(&source, 0, source.text.chars().count());
+				errors.push(Diagnostic::error("expected exercise, prompt, reference, or test")
+					.at(Some(&span)));
+				continue;
+			},
+		};
+		let rest: String = source.text.chars().skip(start).collect();
+		match keyword {
+			"exercise" => name = Some(rest.trim().to_string()),
+			"prompt" => prompt.extend(rest.lines().map(|line| line.trim().to_string())),
+			"reference" => references.push((source.clone(), start)),
+			"test" => tests.push(rest.trim().to_string()),
+			_ => {
+				let indent = source.text.chars().take_while(|ch| ch.is_whitespace()).count();
+				let span = Span::new(&source, indent, start);
+				errors.push(Diagnostic::error(format!("unknown statement: {}", keyword))
+					.at(Some(&span))
+					.help("expected exercise, prompt, reference, or test"));
+			},
+		}
+	}
+
+	let name = match name {
+		Some(name) => name,
+		None => {
+			errors.push(Diagnostic::error(format!("{} doesn't say which definition to write", file))
+				.help("name it like `exercise ISEVEN`"));
+			return Err(errors);
+		},
+	};
+
+	let mut interpreter = Interpreter::new();
+	let mut found = false;
+	for &(ref source, start) in references.iter() {
+		match interpreter.define(source, start) {
+			Ok(defined) => found = found || defined == name,
+			Err(diagnostics) => errors.extend(diagnostics),
+		}
+	}
+	if !found && errors.is_empty() {
+		errors.push(Diagnostic::error(format!("no reference for {}", name))
+			.help(format!("add one like `reference {} := ...`", name)));
+	}
+	if !errors.is_empty() {
+		return Err(errors);
+	}
+	Ok(Exercise {
+		name,
+		prompt: prompt.join("\n"),
+		references,
+		tests,
+	})
+}
+
+/// Outcome of one test.
+pub struct TestResult {
+	/// The term that was evaluated, like `ISEVEN 7`.
+	pub input: String,
+	pub expected: Result<AstNode, String>,
+	pub actual: Result<AstNode, String>,
+}
+
+impl TestResult {
+	/// Results are compared up to eta-conversion, so that
+	/// `\f.f` counts as 1.
+	pub fn passed(&self) -> bool {
+		match (&self.expected, &self.actual) {
+			(Ok(expected), Ok(actual)) => reduction::alpha_equivalent(
+				&reduction::eta_reduce(expected), &reduction::eta_reduce(actual)),
+			_ => false,
+		}
+	}
+}
+
+pub struct Report {
+	pub exercise: String,
+	pub prompt: String,
+	/// Problems with the student's script.
+	pub problems: Vec<Diagnostic>,
+	pub tests: Vec<TestResult>,
+}
+
+impl Report {
+	pub fn passed(&self) -> usize {
+		self.tests.iter().filter(|test| test.passed()).count()
+	}
+
+	/// Fraction of passed tests.
+	pub fn score(&self) -> f64 {
+		if self.tests.is_empty() {
+			0.0
+		} else {
+			self.passed() as f64 / self.tests.len() as f64
+		}
+	}
+
+	pub fn to_json(&self) -> Json {
+		let result = |result: &Result<AstNode, String>| match result {
+			Ok(node) => Json::string(&describe(node)),
+			&Err(..) => Json::Null,
+		};
+		let error = |result: &Result<AstNode, String>| match result {
+			&Ok(..) => Json::Null,
+			Err(message) => Json::string(message),
+		};
+		Json::object(vec![
+			("exercise", Json::string(&self.exercise)),
+			("prompt", Json::string(&self.prompt)),
+			("passed", Json::Number(self.passed() as f64)),
+			("total", Json::Number(self.tests.len() as f64)),
+			("score", Json::Number(self.score())),
+			("problems", Json::Array(self.problems.iter().map(|problem| Json::object(vec![
+				("message", Json::string(&problem.message)),
+				("location", problem.span.as_ref().map_or(Json::Null, |span| Json::String(span.location()))),
+			])).collect())),
+			("tests", Json::Array(self.tests.iter().map(|test| Json::object(vec![
+				("input", Json::string(&test.input)),
+				("passed", Json::Bool(test.passed())),
+				("expected", result(&test.expected)),
+				("actual", result(&test.actual)),
+				("error", error(&test.actual)),
+				("reference_error", error(&test.expected)),
+			])).collect())),
+		])
+	}
+}
+
+/// Shows the value a Church encoding stands for, or the term itself.
+/// Like results, encodings are compared up to eta-conversion.
+fn describe(node: &AstNode) -> String {
+	let eta = reduction::Eta::Reduce;
+	match (numeric_value_eta(node, eta), boolean_value_eta(node, eta)) {
+		(Some(0), _) => "0 / FALSE".to_string(),
+		(Some(num), _) => num.to_string(),
+		(None, Some(true)) => "TRUE".to_string(),
+		_ => pretty_string(node),
+	}
+}
+
+/// Writes the report as text for people.
+impl fmt::Display for Report {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for problem in self.problems.iter() {
+			writeln!(f, "{}", problem)?;
+		}
+		writeln!(f, "exercise {}: {}/{} tests passed ({:.0}%)",
+			self.exercise, self.passed(), self.tests.len(), self.score() * 100.0)?;
+		for test in self.tests.iter() {
+			if test.passed() {
+				writeln!(f, "  ok   {}", test.input)?;
+				continue;
+			}
+			match (&test.expected, &test.actual) {
+				(Err(message), _) => writeln!(f, "  FAIL {}: the reference failed: {}", test.input, message)?,
+				(_, Err(message)) => writeln!(f, "  FAIL {}: {}", test.input, message)?,
+				(Ok(expected), Ok(actual)) => writeln!(f, "  FAIL {}: expected {}, got {}",
+					test.input, describe(expected), describe(actual))?,
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Grades the student's script: its definitions are loaded into a fresh
+/// interpreter, the references into another one, and the definition the
+/// exercise asks for is compared with the reference on each test.
+pub fn grade(exercise: &Exercise, file: &str, script: &str) -> Report {
+	let mut reference = Interpreter::new();
+	reference.set_quiet(true);
+	reference.load_prelude();
+	for &(ref source, start) in exercise.references.iter() {
+		// references were checked when parsing the exercise
+		let _ = reference.define(source, start);
+	}
+
+	let mut student = Interpreter::new();
+	student.set_quiet(true);
+	student.load_prelude();
+	let mut problems = student.load_definitions(file, script);
+	if !student.is_defined(&exercise.name) {
+		problems.push(Diagnostic::error(format!("{} is not defined", exercise.name))
+			.help(format!("define it like `{} := ...`", exercise.name)));
+	}
+
+	let tests = exercise.tests.iter().map(|args| {
+		let input = format!("{} {}", exercise.name, args);
+		TestResult {
+			expected: reference.evaluate_source(&input, FUEL),
+			actual: student.evaluate_source(&input, FUEL),
+			input,
+		}
+	}).collect();
+	Report {
+		exercise: exercise.name.clone(),
+		prompt: exercise.prompt.clone(),
+		problems,
+		tests,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const EXERCISE: &str = "\
+exercise ISEVEN
+prompt Define ISEVEN, which is TRUE for even numbers
+  and FALSE for odd ones.
+reference ISEVEN := \\n.n NOT TRUE
+test 0
+test 7
+test 10
+";
+
+	fn exercise() -> Exercise {
+		parse_exercise("iseven.exercise", EXERCISE).ok().unwrap()
+	}
+
+	#[test]
+	fn passing_solutions() {
+		let report = grade(&exercise(), "solution.lam", "ISEVEN := \\n.n (\\b.b FALSE TRUE) TRUE\n");
+		assert_eq!(report.passed(), 3);
+		assert_eq!(report.score(), 1.0);
+		assert_eq!(report.to_string(), "\
+exercise ISEVEN: 3/3 tests passed (100%)
+  ok   ISEVEN 0
+  ok   ISEVEN 7
+  ok   ISEVEN 10
+");
+	}
+
+	#[test]
+	fn failing_solutions() {
+		let report = grade(&exercise(), "solution.lam", "ISEVEN := \\n.ZERO (PRED n)\n");
+		assert_eq!(report.passed(), 2);
+		assert_eq!(report.to_json().to_string(), concat!(
+			r#"{"exercise":"ISEVEN","#,
+			r#""prompt":"Define ISEVEN, which is TRUE for even numbers\nand FALSE for odd ones.","#,
+			r#""passed":2,"total":3,"score":0.6666666666666666,"problems":[],"tests":["#,
+			r#"{"input":"ISEVEN 0","passed":true,"expected":"TRUE","actual":"TRUE","error":null,"reference_error":null},"#,
+			r#"{"input":"ISEVEN 7","passed":true,"expected":"0 / FALSE","actual":"0 / FALSE","error":null,"reference_error":null},"#,
+			r#"{"input":"ISEVEN 10","passed":false,"expected":"TRUE","actual":"0 / FALSE","error":null,"reference_error":null}]}"#));
+	}
+
+	#[test]
+	fn missing_definitions() {
+		let report = grade(&exercise(), "solution.lam", "ISODD := \\n.n NOT FALSE\n");
+		assert_eq!(report.score(), 0.0);
+		assert_eq!(report.problems[0].message, "ISEVEN is not defined");
+		assert_eq!(report.tests[0].actual.as_ref().err().unwrap(), "unknown function: ISEVEN");
+	}
+}
@@ -0,0 +1,271 @@
+use std::fmt;
+
+/// A JSON value. Object members keep their order.
+#[derive(Clone)]
+pub enum Json {
+	Null,
+	Bool(bool),
+	Number(f64),
+	String(String),
+	Array(Vec<Json>),
+	Object(Vec<(String, Json)>),
+}
+
+impl Json {
+	pub fn string(value: &str) -> Json {
+		Json::String(value.to_string())
+	}
+
+	pub fn object(members: Vec<(&str, Json)>) -> Json {
+		Json::Object(members.into_iter()
+			.map(|(key, value)| (key.to_string(), value))
+			.collect())
+	}
+
+	/// Looks up an object member.
+	pub fn get(&self, key: &str) -> Option<&Json> {
+		match self {
+			Json::Object(members) => members.iter()
+				.find(|member| member.0 == key)
+				.map(|member| &member.1),
+			_ => None,
+		}
+	}
+
+	pub fn as_str(&self) -> Option<&str> {
+		match self {
+			Json::String(value) => Some(value),
+			_ => None,
+		}
+	}
+
+	/// Returns the number if it is a non-negative integer
+	/// that fits into `u32`.
+	pub fn as_u32(&self) -> Option<u32> {
+		match self {
+			&Json::Number(num) if (0.0..=4294967295.0).contains(&num) && num.fract() == 0.0 =>
+				Some(num as u32),
+			_ => None,
+		}
+	}
+}
+
+fn write_string(f: &mut fmt::Formatter, value: &str) -> fmt::Result {
+	write!(f, "\"")?;
+	for ch in value.chars() {
+		match ch {
+			'"' => write!(f, "\\\"")?,
+			'\\' => write!(f, "\\\\")?,
+			'\n' => write!(f, "\\n")?,
+			'\r' => write!(f, "\\r")?,
+			'\t' => write!(f, "\\t")?,
+			ch if (ch as u32) < 0x20 => (write!(f, "\\u{:04x}", ch as u32))?,
+			ch => write!(f, "{}", ch)?,
+		}
+	}
+	write!(f, "\"")
+}
+
+/// Writes compact JSON on a single line.
+impl fmt::Display for Json {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			&Json::Null => write!(f, "null"),
+			&Json::Bool(value) => write!(f, "{}", value),
+			&Json::Number(num) if num.is_finite() => write!(f, "{}", num),
+			&Json::Number(..) => write!(f, "null"),
+			Json::String(value) => write_string(f, value),
+			Json::Array(items) => {
+				write!(f, "[")?;
+				for (index, item) in items.iter().enumerate() {
+					if index > 0 {
+						write!(f, ",")?;
+					}
+					write!(f, "{}", item)?;
+				}
+				write!(f, "]")
+			},
+			Json::Object(members) => {
+				write!(f, "{{")?;
+				for (index, (key, value)) in members.iter().enumerate() {
+					if index > 0 {
+						write!(f, ",")?;
+					}
+					write_string(f, key)?;
+					write!(f, ":{}", value)?;
+				}
+				write!(f, "}}")
+			},
+		}
+	}
+}
+
+struct Reader {
+	chars: Vec<char>,
+	position: usize,
+}
+
+impl Reader {
+	fn error<T>(&self, message: &str) -> Result<T, String> {
+		Err(format!("{} at character {}", message, self.position + 1))
+	}
+
+	fn skip_whitespace(&mut self) {
+		while self.position < self.chars.len() && self.chars[self.position].is_whitespace() {
+			self.position += 1;
+		}
+	}
+
+	fn peek(&mut self) -> Option<char> {
+		self.skip_whitespace();
+		self.chars.get(self.position).cloned()
+	}
+
+	fn expect(&mut self, expected: char) -> Result<(), String> {
+		if self.peek() == Some(expected) {
+			self.position += 1;
+			Ok(())
+		} else {
+			self.error(&format!("expected {}", expected))
+		}
+	}
+
+	fn keyword(&mut self, word: &str, value: Json) -> Result<Json, String> {
+		let end = self.position + word.chars().count();
+		let found: String = self.chars[self.position..end.min(self.chars.len())].iter().cloned().collect();
+		if found == word {
+			self.position = end;
+			Ok(value)
+		} else {
+			self.error("invalid value")
+		}
+	}
+
+	fn number(&mut self) -> Result<Json, String> {
+		let start = self.position;
+		while self.position < self.chars.len() {
+			let ch = self.chars[self.position];
+			if ch.is_ascii_digit() || "+-.eE".contains(ch) {
+				self.position += 1;
+			} else {
+				break;
+			}
+		}
+		let text: String = self.chars[start..self.position].iter().cloned().collect();
+		match text.parse::<f64>() {
+			Ok(num) => Ok(Json::Number(num)),
+			Err(..) => {
+				self.position = start;
+				self.error("invalid number")
+			},
+		}
+	}
+
+	fn string(&mut self) -> Result<String, String> {
+		self.expect('"')?;
+		let mut result = String::new();
+		loop {
+			let ch = match self.chars.get(self.position) {
+				Some(&ch) => ch,
+				None => return self.error("unterminated string"),
+			};
+			self.position += 1;
+			match ch {
+				'"' => return Ok(result),
+				'\\' => {
+					let escaped = self.chars.get(self.position).cloned();
+					self.position += 1;
+					match escaped {
+						Some('"') => result.push('"'),
+						Some('\\') => result.push('\\'),
+						Some('/') => result.push('/'),
+						Some('n') => result.push('\n'),
+						Some('r') => result.push('\r'),
+						Some('t') => result.push('\t'),
+						Some('b') => result.push('\u{8}'),
+						Some('f') => result.push('\u{c}'),
+						Some('u') => {
+							let end = self.position + 4;
+							if end > self.chars.len() {
+								return self.error("invalid escape");
+							}
+							let hex: String = self.chars[self.position..end].iter().cloned().collect();
+							self.position = end;
+							match u32::from_str_radix(&hex, 16).ok().and_then(::std::char::from_u32) {
+								Some(ch) => result.push(ch),
+								None => return self.error("invalid escape"),
+							}
+						},
+						_ => return self.error("invalid escape"),
+					}
+				},
+				ch => result.push(ch),
+			}
+		}
+	}
+
+	fn value(&mut self) -> Result<Json, String> {
+		match self.peek() {
+			Some('{') => {
+				self.position += 1;
+				let mut members = Vec::new();
+				if self.peek() == Some('}') {
+					self.position += 1;
+					return Ok(Json::Object(members));
+				}
+				loop {
+					let key = self.string()?;
+					self.expect(':')?;
+					let value = self.value()?;
+					members.push((key, value));
+					match self.peek() {
+						Some(',') => self.position += 1,
+						Some('}') => {
+							self.position += 1;
+							return Ok(Json::Object(members));
+						},
+						_ => return self.error("expected , or }"),
+					}
+				}
+			},
+			Some('[') => {
+				self.position += 1;
+				let mut items = Vec::new();
+				if self.peek() == Some(']') {
+					self.position += 1;
+					return Ok(Json::Array(items));
+				}
+				loop {
+					items.push(self.value()?);
+					match self.peek() {
+						Some(',') => self.position += 1,
+						Some(']') => {
+							self.position += 1;
+							return Ok(Json::Array(items));
+						},
+						_ => return self.error("expected , or ]"),
+					}
+				}
+			},
+			Some('"') => Ok(Json::String(self.string()?)),
+			Some('t') => self.keyword("true", Json::Bool(true)),
+			Some('f') => self.keyword("false", Json::Bool(false)),
+			Some('n') => self.keyword("null", Json::Null),
+			Some(ch) if ch == '-' || ch.is_ascii_digit() => self.number(),
+			Some(..) => self.error("unexpected character"),
+			None => self.error("unexpected end of input"),
+		}
+	}
+}
+
+pub fn parse(source: &str) -> Result<Json, String> {
+	let mut reader = Reader {
+		chars: source.chars().collect(),
+		position: 0,
+	};
+	let value = reader.value()?;
+	match reader.peek() {
+		None => Ok(value),
+		Some(..) => reader.error("expected end of input"),
+	}
+}
@@ -0,0 +1,109 @@
+#[cfg(feature = "serde")]
+extern crate serde;
+
+// first, so that its `print!` and `println!` are used by the others
+#[macro_use]
+pub mod output;
+
+pub mod blc;
+pub mod builder;
+pub mod check;
+pub mod church;
+pub mod combinators;
+pub mod debugger;
+pub mod diagnostic;
+pub mod dot;
+pub mod foreign;
+pub mod format;
+pub mod formatter;
+pub mod golden;
+pub mod grading;
+pub mod json;
+pub mod lint;
+pub mod native;
+pub mod parser;
+pub mod printer;
+pub mod property;
+pub mod reduction;
+pub mod runtime;
+pub mod stream;
+pub mod types;
+
+#[derive(Clone)]
+pub enum AstNode {
+	FreeVariable(char),
+	BoundVariable(u32),
+	Application(Box<AstNode>, Box<AstNode>),
+	Function(Box<AstNode>),
+	Name(String),
+	/// Numeric literal kept as a native integer.
+	Integer(u32),
+	Builtin(native::Builtin),
+	Foreign(foreign::Primitive),
+	/// The term together with the source it was parsed from. Only
+	/// the parser adds these, and they are gone once names are
+	/// replaced. A function is spanned by its parameter.
+	Spanned(diagnostic::Span, Box<AstNode>),
+	/// Function inlined from the named definition. These are only
+	/// added when profiling, so that the reducer can tell which
+	/// definition each redex came from.
+	Origin(std::rc::Rc<str>, Box<AstNode>),
+}
+
+impl AstNode {
+	/// Where the term comes from, if the parser recorded it.
+	pub fn span(&self) -> Option<&diagnostic::Span> {
+		match self {
+			AstNode::Spanned(span, _) => Some(span),
+			_ => None,
+		}
+	}
+
+	/// The term without its span.
+	pub fn unspanned(&self) -> &AstNode {
+		match self {
+			AstNode::Spanned(_, node) => node.unspanned(),
+			node => node,
+		}
+	}
+}
+
+/// Formats the term the same way as `pretty_print`.
+pub fn pretty_string(node: &AstNode) -> String {
+	printer::render(node, &printer::Ascii)
+}
+
+pub fn pretty_print(node: &AstNode) {
+	print!("{}", pretty_string(node));
+}
+
+/// Prints node contents. As the parser mangles bound 
+/// variable names, this prints the internal format, 
+/// so it usually used for debugging.
+#[allow(dead_code)]
+pub fn print_node(node: &AstNode) {
+	match node {
+		&AstNode::FreeVariable(ch) => print!("{}", ch),
+		&AstNode::BoundVariable(v) => print!("{}", v),
+		AstNode::Function(body) => {
+			print!("(\\");
+			print_node(body);
+			print!(")");
+		},
+		AstNode::Application(a, b) => {
+			print!("(");
+			print_node(a);
+			print!(" ");
+			print_node(b);
+			print!(")");
+		},
+		AstNode::Name(name) => {
+			print!("{}", name);	
+		},
+		&AstNode::Integer(num) => print!("{}", num),
+		&AstNode::Builtin(builtin) => print!("{}", builtin.symbol()),
+		AstNode::Foreign(primitive) => print!("{}", primitive.name),
+		AstNode::Spanned(_, node) => print_node(node),
+		AstNode::Origin(_, node) => print_node(node),
+	}
+}
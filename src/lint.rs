@@ -0,0 +1,145 @@
+use std::collections::HashSet;
+use AstNode;
+use diagnostic::{Diagnostic, Span};
+use reduction::uses_variable;
+
+/// Names a binder in messages by its span, which is the parameter.
+fn binder_name(span: Option<&Span>) -> String {
+	match span {
+		Some(span) => format!("binder {}", span.text()),
+		None => "a binder".to_string(),
+	}
+}
+
+/// `binders` holds the spans of enclosing parameters, innermost
+/// last, and `span` is the span of the node if it has one.
+fn check_walk(node: &AstNode, span: Option<&Span>, binders: &mut Vec<Option<Span>>,
+              definition: Option<&str>, warnings: &mut Vec<Diagnostic>) {
+	let in_definition = |diagnostic: Diagnostic| match (definition, span) {
+		// without a span there is no other way to tell where it is
+		(Some(name), None) => diagnostic.note(format!("in the definition of {}", name)),
+		_ => diagnostic,
+	};
+	match node {
+		AstNode::Spanned(span, node) =>
+			check_walk(node, Some(span), binders, definition, warnings),
+		AstNode::Function(body) => {
+			if let Some(span) = span {
+				let outer = binders.iter().rev()
+					.filter_map(|binder| binder.as_ref())
+					.find(|binder| binder.text() == span.text());
+				if let Some(outer) = outer {
+					warnings.push(Diagnostic::warning(format!("{} shadows an outer one", binder_name(Some(span))))
+						.at(Some(span))
+						.label(outer.clone(), "outer one bound here")
+						.help("rename one of them"));
+				}
+			}
+			if !uses_variable(body, 0) {
+				warnings.push(in_definition(
+					Diagnostic::warning(format!("{} is never used", binder_name(span))).at(span)));
+			}
+			binders.push(span.cloned());
+			check_walk(body, None, binders, definition, warnings);
+			binders.pop();
+		},
+		AstNode::Application(a, b) => {
+			check_walk(a, None, binders, definition, warnings);
+			check_walk(b, None, binders, definition, warnings);
+		},
+		&AstNode::FreeVariable(ch) if definition.is_some() => {
+			warnings.push(in_definition(Diagnostic::warning(format!("free variable {}", ch))
+				.at(span)
+				.help("free variables are kept as they are, this might be a typo")));
+		},
+		_ => {},
+	}
+}
+
+/// Warns about binders in the term that are never used or that shadow
+/// an outer one. For bodies of definitions free variables are warned
+/// about too. Shadowing is only noticed in terms that have spans.
+pub fn check_term(node: &AstNode, definition: Option<&str>) -> Vec<Diagnostic> {
+	let mut warnings = Vec::new();
+	check_walk(node, None, &mut Vec::new(), definition, &mut warnings);
+	warnings
+}
+
+/// Adds the names the term refers to.
+pub fn collect_references(node: &AstNode, names: &mut HashSet<String>) {
+	match node {
+		AstNode::Name(name) => {
+			names.insert(name.clone());
+		},
+		AstNode::Function(body) => collect_references(body, names),
+		AstNode::Application(a, b) => {
+			collect_references(a, names);
+			collect_references(b, names);
+		},
+		AstNode::Spanned(_, node) => collect_references(node, names),
+		_ => {},
+	}
+}
+
+/// Span of the name in the statement a definition was parsed from,
+/// if it has spans.
+pub fn definition_span(node: &AstNode) -> Option<Span> {
+	let source = match node.span() {
+		Some(span) => span.source.clone(),
+		None => return None,
+	};
+	let index = source.text.find(":=")?;
+	let before = &source.text[..index];
+	let start = before.chars().take_while(|ch| ch.is_whitespace()).count();
+	let end = before.trim_end().chars().count();
+	Some(Span::new(&source, start, end))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use diagnostic::Source;
+	use parser::{self, Syntax};
+	use runtime::Interpreter;
+	use output;
+
+	fn warnings(text: &str, definition: Option<&str>) -> Vec<String> {
+		let source = Source::new(text);
+		let node = parser::parse_spanned(&source, 0, Syntax::Lambda).ok().unwrap();
+		check_term(&node, definition).iter()
+			.map(|warning| format!("{} at {}", warning.message, warning.span.as_ref().unwrap().location()))
+			.collect()
+	}
+
+	#[test]
+	fn unused_binders() {
+		assert_eq!(warnings("\\x.\\y.x", None), ["binder y is never used at line 1, column 5"]);
+		assert!(warnings("\\x.\\y.y x", None).is_empty());
+	}
+
+	#[test]
+	fn shadowed_binders() {
+		assert_eq!(warnings("\\x.\\y.x (\\x.x y)", None), ["binder x shadows an outer one at line 1, column 11"]);
+	}
+
+	#[test]
+	fn free_variables_in_definitions() {
+		assert!(warnings("\\x.x z", None).is_empty());
+		assert_eq!(warnings("\\x.x z", Some("F")), ["free variable z at line 1, column 6"]);
+	}
+
+	#[test]
+	fn unused_definitions() {
+		let mut interpreter = Interpreter::new();
+		interpreter.set_quiet(true);
+		interpreter.load_prelude();
+		assert!(interpreter.eval_line("TWICE := \\f.\\x.f (f x x)"));
+		assert!(interpreter.eval_line("MAIN := TWICE I"));
+		assert!(interpreter.eval_line("SPARE := \\x.\\y.y y"));
+		let (success, output) = output::capture(|| interpreter.eval_line(":lint"));
+		assert!(!success);
+		assert!(output.contains("[Warning] SPARE is never used\n"), "{}", output);
+		assert!(!output.contains("TWICE is never used"), "{}", output);
+		assert!(!output.contains("MAIN is never used"), "{}", output);
+	}
+}
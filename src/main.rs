@@ -1,132 +1,221 @@
-mod parser;
-mod reduction;
-mod runtime;
+extern crate lambda;
 
+use std::env;
+use std::fs::File;
 use std::io;
+use std::process;
 use std::io::prelude::*;
-use runtime::Interpreter;
+use lambda::{blc, formatter, golden, grading, stream};
+use lambda::runtime::Interpreter;
 
-pub enum AstNode {
-	FreeVariable(char),
-	BoundVariable(u32),
-	Application(Box<AstNode>, Box<AstNode>),
-	Function(Box<AstNode>),
-	Name(String),
+fn run_stream(args: &[String]) -> bool {
+	let mut interpreter = Interpreter::new();
+	interpreter.set_quiet(true);
+	interpreter.load_prelude();
+	if !interpreter.load_file(&args[0]) {
+		return false;
+	}
+	
+	let name = args.get(1).map(|s| &**s).unwrap_or("MAIN");
+	let program = match interpreter.parse_expression(name) {
+		Some(program) => program,
+		None => return false,
+	};
+	match stream::run(&program) {
+		Ok(()) => true,
+		Err(message) => {
+			println!("[Error] {}", message);
+			false
+		},
+	}
 }
 
-fn pretty_print_walk(node: &AstNode, current_depth: u32, in_application: bool) {
-	match node {
-		&AstNode::Application(ref a, ref b) => {
-			pretty_print_walk(&**a, current_depth, true);
-			print!(" ");
-			match **b {
-				AstNode::Application(..) => {
-					print!("(");
-					pretty_print_walk(&**b, current_depth, true);
-					print!(")");
-				},
-				_ => {
-					pretty_print_walk(&**b, current_depth, true);
-				},
-			}
-		},
-		&AstNode::BoundVariable(num) => {
-			let ch = std::char::from_u32(
-				current_depth - num - 1 + ('a' as u32));
-			print!("{}", ch.unwrap_or('?'));
-		},
-		&AstNode::FreeVariable(ch) => {
-			print!("{}", ch);
-		},
-		&AstNode::Function(ref body) => {
-			let param = std::char::from_u32(current_depth + ('a' as u32))
-				.unwrap_or('?');
-			if in_application {
-				print!("(\\{}.", param);
-			} else {
-				print!("\\{}.", param);
-			}
-			pretty_print_walk(&**body, current_depth + 1, false);
-			if in_application {
-				print!(")");
-			}
-		},
-		&AstNode::Name(ref name) => {
-			print!("{}", name);	
-		},
+/// Formats the files in place, or with `--check` only reports
+/// the ones that aren't formatted.
+fn run_format(args: &[String]) -> bool {
+	let check = args.iter().any(|arg| arg == "--check");
+	let mut success = true;
+	for path in args.iter().filter(|arg| *arg != "--check") {
+		let mut source = String::new();
+		if let Err(e) = File::open(path).and_then(|mut f| f.read_to_string(&mut source)) {
+			println!("[Error] failed to read {}: {}", path, e);
+			success = false;
+			continue;
+		}
+		
+		let formatted = match formatter::format_source(path, &source) {
+			Ok(formatted) => formatted,
+			Err(diagnostics) => {
+				for diagnostic in diagnostics {
+					println!("{}", diagnostic);
+				}
+				success = false;
+				continue;
+			},
+		};
+		if formatted == source {
+			continue;
+		}
+		
+		if check {
+			println!("{} is not formatted", path);
+			success = false;
+		} else if let Err(e) = File::create(path).and_then(|mut f| f.write_all(formatted.as_bytes())) {
+			println!("[Error] failed to write {}: {}", path, e);
+			success = false;
+		}
 	}
+	success
 }
 
-fn pretty_print(node: &AstNode) {
-	pretty_print_walk(node, 0, false);
+/// Runs the script without starting the interpreter, failing if
+/// any of its statements did.
+fn run_script(path: &str) -> bool {
+	let mut interpreter = Interpreter::new();
+	interpreter.set_quiet(true);
+	interpreter.load_prelude();
+	interpreter.load_file(path)
 }
 
-/// Prints node contents. As the parser mangles bound 
-/// variable names, this prints the internal format, 
-/// so it usually used for debugging.
-#[allow(dead_code)]
-fn print_node(node: &AstNode) {
-	match node {
-		&AstNode::FreeVariable(ch) => print!("{}", ch),
-		&AstNode::BoundVariable(v) => print!("{}", v),
-		&AstNode::Function(ref body) => {
-			print!("(\\");
-			print_node(&**body);
-			print!(")");
+fn run_tests(directory: &str) -> bool {
+	match env::current_exe() {
+		Ok(program) => golden::run_tests(&program, directory),
+		Err(e) => {
+			println!("[Error] failed to find the interpreter: {}", e);
+			false
 		},
-		&AstNode::Application(ref a, ref b) => {
-			print!("(");
-			print_node(&**a);
-			print!(" ");
-			print_node(&**b);
-			print!(")");
+	}
+}
+
+fn read_file(path: &str) -> Option<String> {
+	let mut contents = String::new();
+	match File::open(path).and_then(|mut f| f.read_to_string(&mut contents)) {
+		Ok(..) => Some(contents),
+		Err(e) => {
+			println!("[Error] failed to read {}: {}", path, e);
+			None
 		},
-		&AstNode::Name(ref name) => {
-			print!("{}", name);	
+	}
+}
+
+/// Grades the student's script against the exercise, printing the
+/// report as text or, with `--json`, as JSON. Without a script only
+/// the prompt is printed.
+fn run_grading(args: &[String]) -> bool {
+	let json = args.iter().any(|arg| arg == "--json");
+	let paths: Vec<&String> = args.iter().filter(|arg| *arg != "--json").collect();
+	if paths.is_empty() || paths.len() > 2 {
+		println!("[Error] usage: lambda grade EXERCISE [SCRIPT] [--json]");
+		return false;
+	}
+	let contents = match read_file(paths[0]) {
+		Some(contents) => contents,
+		None => return false,
+	};
+	let exercise = match grading::parse_exercise(paths[0], &contents) {
+		Ok(exercise) => exercise,
+		Err(diagnostics) => {
+			for diagnostic in diagnostics {
+				println!("{}", diagnostic);
+			}
+			return false;
+		},
+	};
+	
+	let path = match paths.get(1) {
+		Some(path) => path,
+		None => {
+			println!("{}", exercise.prompt);
+			return true;
 		},
+	};
+	let script = match read_file(path) {
+		Some(script) => script,
+		None => return false,
+	};
+	let report = grading::grade(&exercise, path, &script);
+	if json {
+		println!("{}", report.to_json());
+	} else {
+		print!("{}", report);
 	}
+	true
+}
+
+/// Lints each file on its own, failing if anything was reported.
+fn run_lint(paths: &[String]) -> bool {
+	let mut success = true;
+	for path in paths {
+		let mut interpreter = Interpreter::new();
+		interpreter.set_quiet(true);
+		interpreter.load_prelude();
+		success = interpreter.lint_file(path) && success;
+	}
+	success
 }
 
 fn main() {
+	let args: Vec<String> = env::args().skip(1).collect();
+	if args.len() == 2 && (args[0] == "--blc" || args[0] == "--blc8") {
+		let success = blc::run_file(&args[1], args[0] == "--blc8");
+		process::exit(if success { 0 } else { 1 });
+	}
+	if args.len() >= 2 && args[0] == "fmt" {
+		let success = run_format(&args[1..]);
+		process::exit(if success { 0 } else { 1 });
+	}
+	if args.len() == 2 && args[0] == "--run" {
+		let success = run_script(&args[1]);
+		process::exit(if success { 0 } else { 1 });
+	}
+	if args.len() == 2 && args[0] == "test" {
+		let success = run_tests(&args[1]);
+		process::exit(if success { 0 } else { 1 });
+	}
+	if !args.is_empty() && args[0] == "grade" {
+		let success = run_grading(&args[1..]);
+		process::exit(if success { 0 } else { 1 });
+	}
+	if args.len() >= 2 && args[0] == "--lint" {
+		let success = run_lint(&args[1..]);
+		process::exit(if success { 0 } else { 1 });
+	}
+	if (args.len() == 2 || args.len() == 3) && args[0] == "--stream" {
+		let success = run_stream(&args[1..]);
+		process::exit(if success { 0 } else { 1 });
+	}
+	
+	let json = args.iter().any(|arg| arg == "--json");
 	let mut input = String::new();
 	let mut interpreter = Interpreter::new();
 	
-	interpreter.eval_line("I := \\x.x");
-	interpreter.eval_line("SUCC := \\nfx.f(nfx)");
-	interpreter.eval_line("ADD := \\mnfx.mf(nfx)");
-	interpreter.eval_line("MUL := \\mnf.m(nf)");
-	interpreter.eval_line("POW := \\be.eb");
-	interpreter.eval_line("PRED := \\nfx.n(\\gh.h(gf))(\\u.x)(\\u.u)");
-	interpreter.eval_line("SUB := \\mn.n PRED m");
-	interpreter.eval_line("TRUE := \\xy.x");
-	interpreter.eval_line("FALSE := \\xy.y");
-	interpreter.eval_line("AND := \\pq.pqp");
-	interpreter.eval_line("OR := \\pq.ppq");
-	interpreter.eval_line("NOT := \\pab.pba");
-	interpreter.eval_line("IF := \\pab.pab");
-	interpreter.eval_line("ZERO := \\n.n(\\x.FALSE)TRUE");
-	interpreter.eval_line("LEQ := \\mn.ZERO (SUB m n)");
-	interpreter.eval_line("EQ := \\mn.AND (LEQ m n) (LEQ n m)");
-	interpreter.eval_line("PAIR := \\xyf.fxy");
-	interpreter.eval_line("FIRST := \\p.p TRUE");
-	interpreter.eval_line("SECOND := \\p.p FALSE");
-	interpreter.eval_line("NIL := \\x.TRUE");
-	interpreter.eval_line("NULL := \\p.p(\\xy.FALSE)");
+	interpreter.set_quiet(json);
+	interpreter.load_prelude();
+	interpreter.set_json(json);
 	
-	interpreter.eval_line("HEAD := \\p.FIRST p");
-	interpreter.eval_line("TAIL := \\p.SECOND p");
-	interpreter.eval_line("CONS := PAIR");
-	interpreter.eval_line("REC := \\f.ff");
-	interpreter.eval_line("REDUCE := REC (\\rfp.IF (NULL (TAIL p)) (HEAD p) (r r f (PAIR (f (HEAD p) (HEAD (TAIL p))) (TAIL (TAIL p)))))");
+	for path in args.iter().filter(|arg| *arg != "--json") {
+		interpreter.load_file(path);
+	}
 	
 	loop {
-		print!("> ");
-		io::stdout().flush().expect("Failed to flush stdout");
+		// in JSON mode every line of output is a JSON object
+		if !json {
+			print!("{}", interpreter.prompt());
+			io::stdout().flush().expect("Failed to flush stdout");
+		}
 		input.clear();
-		io::stdin().read_line(&mut input).expect("Failed to read line");
-		// when reading from stdin strings always
-		// have a trailing newline for some reason
-		assert_eq!(input.pop().unwrap_or('\0'), '\n');
+		let read = io::stdin().read_line(&mut input).expect("Failed to read line");
+		if read == 0 {
+			// end of input
+			if !json {
+				println!();
+			}
+			break;
+		}
+		// lines read from stdin keep their trailing newline
+		if input.ends_with('\n') {
+			input.pop();
+		}
 		
 		interpreter.eval_line(&input);
 	}
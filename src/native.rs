@@ -0,0 +1,186 @@
+use AstNode;
+use runtime::numeric_value;
+
+/// Arithmetic constants that reduce natively when applied to two
+/// numbers. Each of them agrees with a prelude definition.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Builtin {
+	Add,
+	Mul,
+	Sub,
+	Eq,
+	Leq,
+}
+
+impl Builtin {
+	pub fn from_symbol(symbol: &str) -> Option<Builtin> {
+		match symbol {
+			"+" => Some(Builtin::Add),
+			"*" => Some(Builtin::Mul),
+			"-" => Some(Builtin::Sub),
+			"==" => Some(Builtin::Eq),
+			"<=" => Some(Builtin::Leq),
+			_ => None,
+		}
+	}
+
+	pub fn symbol(self) -> &'static str {
+		match self {
+			Builtin::Add => "+",
+			Builtin::Mul => "*",
+			Builtin::Sub => "-",
+			Builtin::Eq => "==",
+			Builtin::Leq => "<=",
+		}
+	}
+
+	/// Name of the prelude definition computing the same thing
+	/// on Church numerals.
+	pub fn prelude_name(self) -> &'static str {
+		match self {
+			Builtin::Add => "ADD",
+			Builtin::Mul => "MUL",
+			Builtin::Sub => "SUB",
+			Builtin::Eq => "EQ",
+			Builtin::Leq => "LEQ",
+		}
+	}
+
+	/// Computes the result, or returns `None` if it doesn't
+	/// fit into a native integer.
+	fn apply(self, a: u32, b: u32) -> Option<AstNode> {
+		match self {
+			Builtin::Add => a.checked_add(b).map(AstNode::Integer),
+			Builtin::Mul => a.checked_mul(b).map(AstNode::Integer),
+			// like SUB, subtraction stops at zero
+			Builtin::Sub => Some(AstNode::Integer(a.saturating_sub(b))),
+			Builtin::Eq => Some(church_boolean(a == b)),
+			Builtin::Leq => Some(church_boolean(a <= b)),
+		}
+	}
+}
+
+fn church_boolean(value: bool) -> AstNode {
+	let var = if value { 1 } else { 0 };
+	AstNode::Function(Box::new(AstNode::Function(
+		Box::new(AstNode::BoundVariable(var)))))
+}
+
+/// Value of a native integer or a Church numeral.
+fn integer_value(node: &AstNode) -> Option<u32> {
+	match node {
+		&AstNode::Integer(num) => Some(num),
+		_ => numeric_value(node),
+	}
+}
+
+/// Performs a delta step if the normalised function is a builtin
+/// applied to one number and the normalised argument is a number.
+pub fn delta_reduce(function: &AstNode, argument: &AstNode) -> Option<AstNode> {
+	match function {
+		AstNode::Application(builtin, a) => match &**builtin {
+			&AstNode::Builtin(builtin) => {
+				let a = integer_value(a)?;
+				let b = integer_value(argument)?;
+				builtin.apply(a, b)
+			},
+			_ => None,
+		},
+		_ => None,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use runtime::{boolean_value, Interpreter};
+
+	const FUEL: usize = 100000;
+
+	fn interpreter(native: bool) -> Interpreter {
+		let mut interpreter = Interpreter::new();
+		interpreter.set_quiet(true);
+		interpreter.load_prelude();
+		if native {
+			assert!(interpreter.eval_line(":native on"));
+		}
+		interpreter
+	}
+
+	fn number(interpreter: &Interpreter, source: &str) -> Option<u32> {
+		integer_value(&interpreter.evaluate_source(source, FUEL).unwrap())
+	}
+
+	fn boolean(interpreter: &Interpreter, source: &str) -> Option<bool> {
+		boolean_value(&interpreter.evaluate_source(source, FUEL).unwrap())
+	}
+
+	#[test]
+	fn builtins_agree_with_the_prelude() {
+		let native = interpreter(true);
+		let pure = interpreter(false);
+		for builtin in [Builtin::Add, Builtin::Mul, Builtin::Sub, Builtin::Eq, Builtin::Leq].iter() {
+			for a in 0..5 {
+				for b in 0..5 {
+					let source = format!("{} {} {}", builtin.symbol(), a, b);
+					let prelude = format!("{} {} {}", builtin.prelude_name(), a, b);
+					// 0 and FALSE are the same term, so numbers are compared
+					// as numbers and comparisons as booleans
+					let (native, pure, expected) = match *builtin {
+						Builtin::Eq | Builtin::Leq => (
+							boolean(&native, &source).map(|b| b as u32),
+							boolean(&pure, &source).map(|b| b as u32),
+							boolean(&pure, &prelude).map(|b| b as u32)),
+						_ => (number(&native, &source), number(&pure, &source), number(&pure, &prelude)),
+					};
+					assert!(expected.is_some(), "{} has no value", prelude);
+					assert_eq!(native, expected, "{} disagrees with the prelude", source);
+					assert_eq!(pure, expected, "{} disagrees with the prelude", source);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn subtraction_stops_at_zero() {
+		let native = interpreter(true);
+		let pure = interpreter(false);
+		assert_eq!(number(&native, "- 2 5"), Some(0));
+		assert_eq!(number(&pure, "SUB 2 5"), Some(0));
+		assert_eq!(number(&pure, "- 2 5"), Some(0));
+	}
+
+	#[test]
+	fn literals_applied_as_functions_are_church_numerals() {
+		let native = interpreter(true);
+		let pure = interpreter(false);
+		for source in ["3 (\\x.x) 4", "2 (+ 3) 1", "ADD 2 (+ 1 1)", "MUL (- 5 2) 2"].iter() {
+			assert_eq!(number(&native, source), number(&pure, source), "{} disagrees with the prelude", source);
+		}
+		assert_eq!(boolean(&native, "ZERO (- 1 1)"), Some(true));
+	}
+
+	#[test]
+	fn overflow_is_left_unreduced() {
+		let native = interpreter(true);
+		for source in ["+ 4294967295 1", "* 65536 65536"].iter() {
+			let node = native.evaluate_source(source, FUEL).unwrap();
+			assert_eq!(integer_value(&node), None, "{} wrapped around", source);
+			match node {
+				AstNode::Application(ref function, _) => match **function {
+					AstNode::Application(ref builtin, _) => assert!(builtin_of(builtin).is_some()),
+					_ => panic!("{} reduced to something else", source),
+				},
+				_ => panic!("{} reduced to something else", source),
+			}
+		}
+		assert_eq!(number(&native, "- 4294967295 1"), Some(4294967294));
+	}
+
+	fn builtin_of(node: &AstNode) -> Option<Builtin> {
+		match *node {
+			AstNode::Builtin(builtin) => Some(builtin),
+			_ => None,
+		}
+	}
+}
@@ -0,0 +1,40 @@
+//! Where the interpreter's output goes. The crate's `print!` and
+//! `println!` shadow the standard ones and write through `print`, so
+//! that the output of a script can be captured, see `capture`.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Write};
+
+thread_local! {
+	static CAPTURED: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+macro_rules! print {
+	($($arg:tt)*) => ($crate::output::print(format_args!($($arg)*)));
+}
+
+macro_rules! println {
+	() => (print!("\n"));
+	($($arg:tt)*) => ($crate::output::print(format_args!("{}\n", format_args!($($arg)*))));
+}
+
+/// Writes to standard output, or to the captured text while `capture`
+/// is running on this thread.
+pub fn print(args: fmt::Arguments) {
+	CAPTURED.with(|captured| match *captured.borrow_mut() {
+		Some(ref mut text) => text.write_fmt(args).expect("failed to capture output"),
+		None => io::stdout().write_fmt(args).expect("failed printing to stdout"),
+	})
+}
+
+/// Runs `f`, returning what it printed instead of printing it.
+pub fn capture<F, T>(f: F) -> (T, String)
+	where F: FnOnce() -> T
+{
+	let outer = CAPTURED.with(|captured| captured.borrow_mut().replace(String::new()));
+	let result = f();
+	let text = CAPTURED.with(|captured| captured.replace(outer)).unwrap_or_default();
+	(result, text)
+}
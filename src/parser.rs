@@ -1,16 +1,255 @@
 use std;
-use std::collections::HashMap;
+use std::rc::Rc;
 use AstNode;
+use blc;
+use diagnostic::{Diagnostic, Source, Span};
+
+/// Source languages the parser accepts. All of them are
+/// translated into the same `AstNode` representation.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Syntax {
+	/// Lambda terms, as in `\xy.x`.
+	Lambda,
+	/// Applications of `S`, `K`, `I` and named combinators.
+	Combinators,
+	/// Chris Barker's Iota: `i` and prefix application `*`.
+	Iota,
+	/// Chris Barker's Jot: strings of `0` and `1`.
+	Jot,
+	/// John Tromp's Binary Lambda Calculus, written as `0` and `1`.
+	Blc,
+}
+
+impl Syntax {
+	pub fn from_name(name: &str) -> Option<Syntax> {
+		match name {
+			"lambda" => Some(Syntax::Lambda),
+			"cl" | "ski" => Some(Syntax::Combinators),
+			"iota" => Some(Syntax::Iota),
+			"jot" => Some(Syntax::Jot),
+			"blc" => Some(Syntax::Blc),
+			_ => None,
+		}
+	}
+	
+	pub fn from_extension(extension: &str) -> Option<Syntax> {
+		match extension {
+			"lam" => Some(Syntax::Lambda),
+			"cl" | "ski" => Some(Syntax::Combinators),
+			"iota" => Some(Syntax::Iota),
+			"jot" => Some(Syntax::Jot),
+			"blc" => Some(Syntax::Blc),
+			_ => None,
+		}
+	}
+	
+	pub fn name(self) -> &'static str {
+		match self {
+			Syntax::Lambda => "lambda",
+			Syntax::Combinators => "cl",
+			Syntax::Iota => "iota",
+			Syntax::Jot => "jot",
+			Syntax::Blc => "blc",
+		}
+	}
+}
+
+/// A token as written in the source, with the whitespace before it.
+pub struct SourceToken {
+	pub leading: String,
+	pub text: String,
+	/// Position of the text, not counting the leading whitespace.
+	pub position: usize,
+}
+
+impl SourceToken {
+	fn end(&self) -> usize {
+		self.position + self.text.chars().count()
+	}
+}
+
+/// Concrete syntax tree of a lambda term. Unlike `AstNode` it keeps
+/// everything as written: binder names, parentheses and whitespace,
+/// so that the source can be reproduced exactly.
+pub enum Cst {
+	Variable(SourceToken),
+	Integer(SourceToken, u32),
+	/// A name or an operator.
+	Name(SourceToken),
+	/// Open parenthesis, the term, and close parenthesis.
+	Parenthesized(SourceToken, Box<Cst>, SourceToken),
+	/// Lambda, parameters, dot, and the body.
+	Function(SourceToken, Vec<SourceToken>, SourceToken, Box<Cst>),
+	/// Two or more terms applied left to right.
+	Application(Vec<Cst>),
+}
+
+/// A parsed term with the whitespace that follows it.
+pub struct SyntaxTree {
+	pub term: Cst,
+	pub trailing: String,
+}
+
+fn write_token(token: &SourceToken, out: &mut String) {
+	out.push_str(&token.leading);
+	out.push_str(&token.text);
+}
+
+fn write_cst(cst: &Cst, out: &mut String) {
+	match cst {
+		&Cst::Variable(ref token) |
+		&Cst::Integer(ref token, _) |
+		&Cst::Name(ref token) => write_token(token, out),
+		Cst::Parenthesized(open, inner, close) => {
+			write_token(open, out);
+			write_cst(inner, out);
+			write_token(close, out);
+		},
+		Cst::Function(lambda, params, dot, body) => {
+			write_token(lambda, out);
+			for param in params.iter() {
+				write_token(param, out);
+			}
+			write_token(dot, out);
+			write_cst(body, out);
+		},
+		Cst::Application(items) => {
+			for item in items.iter() {
+				write_cst(item, out);
+			}
+		},
+	}
+}
+
+/// Positions of the first and the last character of the term.
+fn cst_range(cst: &Cst) -> (usize, usize) {
+	match cst {
+		&Cst::Variable(ref token) |
+		&Cst::Integer(ref token, _) |
+		&Cst::Name(ref token) => (token.position, token.end()),
+		Cst::Parenthesized(open, _, close) => (open.position, close.end()),
+		Cst::Function(lambda, _, _, body) => (lambda.position, cst_range(body).1),
+		Cst::Application(items) =>
+			(cst_range(&items[0]).0, cst_range(&items[items.len() - 1]).1),
+	}
+}
+
+/// Wraps the node with its span if spans are recorded, positions
+/// are offset by the place where the parsed text starts.
+fn spanned(node: AstNode, source: Option<(&Rc<Source>, usize)>, start: usize, end: usize) -> AstNode {
+	match source {
+		Some((source, offset)) =>
+			AstNode::Spanned(Span::new(source, start + offset, end + offset), Box::new(node)),
+		None => node,
+	}
+}
+
+/// Resolves variables to de Bruijn indices, `scope` holds
+/// the parameters of enclosing functions.
+fn lower(cst: &Cst, scope: &mut Vec<char>, source: Option<(&Rc<Source>, usize)>) -> AstNode {
+	match cst {
+		Cst::Variable(token) => {
+			let ch = token.text.chars().next().unwrap();
+			let node = match scope.iter().rev().position(|&param| param == ch) {
+				Some(index) => AstNode::BoundVariable(index as u32),
+				None => AstNode::FreeVariable(ch),
+			};
+			spanned(node, source, token.position, token.end())
+		},
+		&Cst::Integer(ref token, num) =>
+			spanned(AstNode::Integer(num), source, token.position, token.end()),
+		Cst::Name(token) =>
+			spanned(AstNode::Name(token.text.clone()), source, token.position, token.end()),
+		Cst::Parenthesized(_, inner, _) => lower(inner, scope, source),
+		Cst::Function(_, params, _, body) => {
+			for param in params.iter() {
+				scope.push(param.text.chars().next().unwrap());
+			}
+			let mut node = lower(body, scope, source);
+			for param in params.iter().rev() {
+				scope.pop();
+				node = spanned(AstNode::Function(Box::new(node)), source, param.position, param.end());
+			}
+			node
+		},
+		Cst::Application(items) => {
+			let start = cst_range(&items[0]).0;
+			let mut node = lower(&items[0], scope, source);
+			for item in items[1..].iter() {
+				let end = cst_range(item).1;
+				node = AstNode::Application(Box::new(node), Box::new(lower(item, scope, source)));
+				node = spanned(node, source, start, end);
+			}
+			node
+		},
+	}
+}
+
+impl SyntaxTree {
+	/// Gives back the source exactly as it was parsed.
+	pub fn to_source(&self) -> String {
+		let mut out = String::new();
+		write_cst(&self.term, &mut out);
+		out.push_str(&self.trailing);
+		out
+	}
+	
+	pub fn to_ast(&self) -> AstNode {
+		lower(&self.term, &mut Vec::new(), None)
+	}
+	
+	/// Like `to_ast`, but every node is spanned. The tree must have
+	/// been parsed from the source starting at `offset`.
+	pub fn to_spanned_ast(&self, source: &Rc<Source>, offset: usize) -> AstNode {
+		lower(&self.term, &mut Vec::new(), Some((source, offset)))
+	}
+}
 
 pub struct ParseError {
 	pub position: usize,
 	pub message: String,
+	/// How the error could be fixed.
+	pub suggestion: Option<String>,
+	/// Another place the suggestion is about, like the
+	/// parenthesis that isn't closed.
+	pub related: Option<usize>,
+}
+
+impl ParseError {
+	pub fn new(position: usize, message: String) -> ParseError {
+		ParseError {
+			position,
+			message,
+			suggestion: None,
+			related: None,
+		}
+	}
+	
+	fn suggest(mut self, suggestion: &str, related: Option<usize>) -> ParseError {
+		self.suggestion = Some(suggestion.to_string());
+		self.related = related;
+		self
+	}
+	
+	/// Points at the place in the source where parsing failed.
+	pub fn diagnostic(self, source: &Rc<Source>) -> Diagnostic {
+		let diagnostic = Diagnostic::error(self.message)
+			.at(Some(&Span::new(source, self.position, self.position)));
+		match (self.suggestion, self.related) {
+			(Some(suggestion), Some(related)) =>
+				diagnostic.label(Span::new(source, related, related + 1), suggestion),
+			(Some(suggestion), None) => diagnostic.help(suggestion),
+			(None, _) => diagnostic,
+		}
+	}
 }
 
 enum TokenContents {
 	Letter(char),
 	Number(u32),
 	Name(String),
+	/// One of `+ * - == <=`, which is kept in the text of the token.
+	Operator,
 	Dot,
 	Lambda,
 	OpenParenth,
@@ -21,11 +260,42 @@ enum TokenContents {
 struct Token {
 	position: usize,
 	contents: TokenContents,
+	/// Whitespace between the previous token and this one.
+	leading: String,
+	/// The token as written in the source.
+	text: String,
 }
 
+impl Token {
+	fn new(position: usize, contents: TokenContents) -> Token {
+		Token {
+			position,
+			contents,
+			leading: String::new(),
+			text: String::new(),
+		}
+	}
+
+	fn source(self) -> SourceToken {
+		SourceToken {
+			leading: self.leading,
+			text: self.text,
+			position: self.position,
+		}
+	}
+}
+
+/// Splits lambda terms into tokens. Comments are looked for before
+/// every token, so they win over the `-` operator: `--` starts a line
+/// comment even right after a term, as in `a --b`, and `{-` starts a
+/// block comment. Subtraction next to a comment needs a space between
+/// them, like `- -- comment` or `- {- comment -}`, and `- -` is two
+/// operators.
 struct Lexer<'a> {
 	data: std::iter::Peekable<std::str::Chars<'a>>,
 	position: usize,
+	/// Characters consumed since the last token.
+	consumed: String,
 }
 
 fn is_whitespace(ch: char) -> bool {
@@ -47,11 +317,34 @@ fn is_digit(ch: char) -> bool {
 	ch >= '0' && ch <= '9'
 }
 
+/// Checks that the name can be bound with `:=` and referred to,
+/// which means it is an uppercase letter followed by uppercase
+/// letters and digits.
+pub fn check_name(name: &str) -> Result<(), &'static str> {
+	if name.is_empty() {
+		return Err("name cannot be empty");
+	}
+	
+	for ch in name.chars() {
+		if !is_digit(ch) && !is_name(ch) {
+			return Err("invalid name");
+		} 
+	}
+	
+	// we checked earlier that the string is not empty
+	if !is_name(name.chars().next().unwrap()) {
+		return Err("invalid name");
+	}
+	
+	Ok(())
+}
+
 impl<'a> Lexer<'a> {
 	fn new(source: &str) -> Lexer {
 		Lexer {
 			data: source.chars().peekable(),
 			position: 0,
+			consumed: String::new(),
 		}
 	}
 	
@@ -64,15 +357,56 @@ impl<'a> Lexer<'a> {
 	}
 	
 	fn advance(&mut self) {
-		self.data.next();
+		if let Some(ch) = self.data.next() {
+			self.consumed.push(ch);
+		}
 		self.position += 1;
 	}
 	
-	fn skip_whitespace(&mut self) {
+	fn peek_second(&self) -> Option<char> {
+		let mut ahead = self.data.clone();
+		ahead.next();
+		ahead.next()
+	}
+	
+	/// Skips whitespace and comments: `--` and `#` comment out the
+	/// rest of the line, and `{- ... -}` comments can be nested.
+	/// A `-` that starts neither is left for `operator_token`.
+	fn skip_whitespace(&mut self) -> Result<(), ParseError> {
 		loop {
-			match self.peek_char() {
-				Some(ch) if is_whitespace(ch) => self.advance(),
-				_ => break,
+			match (self.peek_char(), self.peek_second()) {
+				(Some(ch), _) if is_whitespace(ch) => self.advance(),
+				(Some('-'), Some('-')) | (Some('#'), _) => {
+					while self.peek_char().is_some_and(|ch| ch != '\n') {
+						self.advance();
+					}
+				},
+				(Some('{'), Some('-')) => self.skip_block_comment()?,
+				_ => return Ok(()),
+			}
+		}
+	}
+	
+	fn skip_block_comment(&mut self) -> Result<(), ParseError> {
+		let start = self.position;
+		let mut depth = 0;
+		loop {
+			match (self.peek_char(), self.peek_second()) {
+				(Some('{'), Some('-')) => {
+					self.advance();
+					self.advance();
+					depth += 1;
+				},
+				(Some('-'), Some('}')) => {
+					self.advance();
+					self.advance();
+					depth -= 1;
+					if depth == 0 {
+						return Ok(());
+					}
+				},
+				(Some(..), _) => self.advance(),
+				(None, _) => return Err(ParseError::new(start, "unterminated block comment".to_string())),
 			}
 		}
 	}
@@ -80,10 +414,7 @@ impl<'a> Lexer<'a> {
 	fn punctuation_token(&mut self, contents: TokenContents) -> Token {
 		let start = self.position;
 		self.advance();
-		Token {
-			position: start,
-			contents: contents,
-		}
+		Token::new(start, contents)
 	}
 	
 	fn name_token(&mut self, start: usize) -> Result<Token, ParseError> {
@@ -94,15 +425,9 @@ impl<'a> Lexer<'a> {
 					name.push(ch);
 					self.advance();
 				},
-				Some(ch) if is_variable(ch) => return Err(ParseError {
-					position: start,
-					message: "names must consist of capital \
-					          letters and numbers".to_string(),
-				}),
-				_ => return Ok(Token {
-					position: start,
-					contents: TokenContents::Name(name),
-				}),
+				Some(ch) if is_variable(ch) => return Err(ParseError::new(start,
+					"names must consist of capital letters and numbers".to_string())),
+				_ => return Ok(Token::new(start, TokenContents::Name(name))),
 			}
 		}
 	}
@@ -115,22 +440,13 @@ impl<'a> Lexer<'a> {
 					accumulator = accumulator * 10 + 
 						(ch as u64) - ('0' as u64);
 					if accumulator > (std::u32::MAX as u64) {
-						return Err(ParseError {
-							position: start,
-							message: "integer literal is too large".to_string(),
-						});
+						return Err(ParseError::new(start, "integer literal is too large".to_string()));
 					}
 					self.advance();
 				}
 				Some(ch) if is_variable(ch) || is_name(ch) => 
-					return Err(ParseError {
-						position: self.position,
-						message: "invalid number".to_string(),
-					}),
-				_ => return Ok(Token {
-					position: start,
-					contents: TokenContents::Number(accumulator as u32),
-				}),
+					return Err(ParseError::new(self.position, "invalid number".to_string())),
+				_ => return Ok(Token::new(start, TokenContents::Number(accumulator as u32))),
 			}
 		} 
 	}
@@ -142,43 +458,67 @@ impl<'a> Lexer<'a> {
 		// skip that initial symbol
 		self.advance();
 		match self.peek_char() {
-			Some(ch) if is_digit(ch) => Err(ParseError {
-				position: self.position,
-				message: "variable can't be immediately \
-				          followed by a number".to_string(),
-			}),
-			Some(ch) if is_name(ch) => Err(ParseError {
-				position: self.position,
-				message: "variable can't be immediately \
-				          followed by a name".to_string(),
-			}),
-			_ => Ok(Token {
-				position: start,
-				contents: TokenContents::Letter(var),
-			})
+			Some(ch) if is_digit(ch) => Err(ParseError::new(self.position,
+				"variable can't be immediately followed by a number".to_string())
+				.suggest("separate them with a space", None)),
+			Some(ch) if is_name(ch) => Err(ParseError::new(self.position,
+				"variable can't be immediately followed by a name".to_string())
+				.suggest("separate them with a space", None)),
+			_ => Ok(Token::new(start, TokenContents::Letter(var)))
+		}
+	}
+	
+	fn operator_token(&mut self, start: usize) -> Result<Token, ParseError> {
+		// this is called when initial symbol is already
+		// found, so unwrapping should be safe
+		let first = self.peek_char().unwrap();
+		self.advance();
+		if first == '=' || first == '<' {
+			match self.peek_char() {
+				Some('=') => self.advance(),
+				_ => return Err(ParseError::new(start, format!("expected = after {}", first))),
+			}
+		}
+		
+		Ok(Token::new(start, TokenContents::Operator))
+	}
+	
+	/// Skips the rest of an invalid token, up to whitespace
+	/// or punctuation.
+	fn skip_invalid(&mut self) {
+		while let Some(ch) = self.peek_char() {
+			match ch {
+				'(' | ')' | '.' | '\\' => return,
+				ch if is_whitespace(ch) => return,
+				_ => self.advance(),
+			}
 		}
 	}
 	
 	fn next_token(&mut self) -> Result<Token, ParseError> {
-		self.skip_whitespace();
+		self.consumed.clear();
+		self.skip_whitespace()?;
+		let leading = std::mem::take(&mut self.consumed);
+		let mut token = self.read_token()?;
+		token.leading = leading;
+		token.text = std::mem::take(&mut self.consumed);
+		Ok(token)
+	}
+	
+	fn read_token(&mut self) -> Result<Token, ParseError> {
 		let token_start = self.position;
 		match self.peek_char() {
-			None => Ok(Token { 
-				position: token_start,
-				contents: TokenContents::End, 
-			}),
+			None => Ok(Token::new(token_start, TokenContents::End)),
 			Some(ch) => match ch {
 				n if is_digit(n) => self.number_token(token_start),
 				l if is_variable(l) => self.variable_token(token_start),
 				n if is_name(n) => self.name_token(token_start),
 				'.' => Ok(self.punctuation_token(TokenContents::Dot)),
 				'\\' => Ok(self.punctuation_token(TokenContents::Lambda)),
+				'+' | '*' | '-' | '=' | '<' => self.operator_token(token_start),
 				'(' => Ok(self.punctuation_token(TokenContents::OpenParenth)),
 				')' => Ok(self.punctuation_token(TokenContents::CloseParenth)),
-				_ => Err(ParseError {
-					position: token_start,
-					message: "invalid token".to_string(),
-				}),
+				_ => Err(ParseError::new(token_start, "invalid token".to_string())),
 			},
 		}
 	}
@@ -188,25 +528,36 @@ struct Parser<'a> {
 	lexer: Lexer<'a>,
 	next_token: Token,
 	has_token: bool,
-	bind_depths: HashMap<char, u32>,
-	current_depth: u32,
+	/// Whether to carry on after invalid tokens.
+	recover: bool,
+	/// Errors parsing carried on after.
+	errors: Vec<ParseError>,
 }
 
 impl<'a> Parser<'a> {
 	fn new(source: &str) -> Parser {
 		Parser {
 			lexer: Lexer::new(source),
-			next_token: Token { position: 0, contents: TokenContents::End },
+			next_token: Token::new(0, TokenContents::End),
 			has_token: false,
-			bind_depths: HashMap::new(),
-			current_depth: 0,
+			recover: false,
+			errors: Vec::new(),
 		}
 	} 
 	
 	fn peek(&mut self) -> Result<&Token, ParseError> {
-		if !self.has_token {
-			self.next_token = try!(self.lexer.next_token());
-			self.has_token = true;
+		while !self.has_token {
+			match self.lexer.next_token() {
+				Ok(token) => {
+					self.next_token = token;
+					self.has_token = true;
+				},
+				Err(error) if self.recover => {
+					self.errors.push(error);
+					self.lexer.skip_invalid();
+				},
+				Err(error) => return Err(error),
+			}
 		}
 		
 		Ok(&(self.next_token))
@@ -221,24 +572,175 @@ impl<'a> Parser<'a> {
 		let old_position = self.next_token.position;
 		let token = std::mem::replace(
 			&mut self.next_token,
-			Token {
-				position: old_position,
-				contents: TokenContents::End 
-			});
+			Token::new(old_position, TokenContents::End));
 		
 		self.has_token = false;
 		return Ok(token);
 	}
 	
 	fn error(&self, message: String) -> ParseError {
-		ParseError {
-			position: self.next_token.position,
-			message: message,
+		ParseError::new(self.next_token.position, message)
+	}
+	
+	/// Records an error that parsing can carry on after.
+	fn report(&mut self, error: ParseError) {
+		self.errors.push(error);
+	}
+	
+	/// Skips tokens up to the `)` closing the current parentheses
+	/// or the end of input.
+	fn synchronize(&mut self) -> Result<(), ParseError> {
+		let mut depth = 0;
+		loop {
+			match self.peek()?.contents {
+				TokenContents::End => return Ok(()),
+				TokenContents::CloseParenth if depth == 0 => return Ok(()),
+				TokenContents::CloseParenth => depth -= 1,
+				TokenContents::OpenParenth => depth += 1,
+				_ => {},
+			}
+			self.consume()?;
+		}
+	}
+	
+	fn at_end(&mut self) -> Result<bool, ParseError> {
+		Ok(matches!(self.peek()?.contents, TokenContents::End | TokenContents::CloseParenth))
+	}
+}
+
+/// Comments and line breaks in the whitespace between tokens.
+pub enum Trivia {
+	/// A line break with the indentation of the next line.
+	Newline(String),
+	/// A comment as written, without the line break
+	/// ending a line comment.
+	Comment(String),
+}
+
+/// Splits whitespace preceding a token, such as `SourceToken::leading`,
+/// into comments and line breaks.
+pub fn trivia(whitespace: &str) -> Vec<Trivia> {
+	let mut lexer = Lexer::new(whitespace);
+	let mut result = Vec::new();
+	loop {
+		lexer.consumed.clear();
+		match (lexer.peek_char(), lexer.peek_second()) {
+			(None, _) => return result,
+			(Some('\n'), _) => {
+				lexer.advance();
+				lexer.consumed.clear();
+				while lexer.peek_char().is_some_and(|ch| ch == ' ' || ch == '\t') {
+					lexer.advance();
+				}
+				result.push(Trivia::Newline(lexer.consumed.clone()));
+			},
+			(Some('-'), Some('-')) | (Some('#'), _) => {
+				while lexer.peek_char().is_some_and(|ch| ch != '\n') {
+					lexer.advance();
+				}
+				result.push(Trivia::Comment(lexer.consumed.clone()));
+			},
+			(Some('{'), Some('-')) => {
+				if lexer.skip_block_comment().is_err() {
+					return result;
+				}
+				result.push(Trivia::Comment(lexer.consumed.clone()));
+			},
+			(Some(..), _) => lexer.advance(),
+		}
+	}
+}
+
+/// Checks if the source has nothing but whitespace and comments.
+pub fn is_blank(source: &str) -> bool {
+	let mut lexer = Lexer::new(source);
+	lexer.skip_whitespace().is_ok() && lexer.peek_char().is_none()
+}
+
+/// If the statement starts with the keyword, like `forall` or
+/// `assert`, returns the position right after it.
+pub fn statement_keyword(text: &str, keyword: &str) -> Option<usize> {
+	let indent = text.chars().take_while(|ch| ch.is_whitespace()).count();
+	let mut rest = text.trim_start().chars();
+	for expected in keyword.chars() {
+		if rest.next() != Some(expected) {
+			return None;
+		}
+	}
+	match rest.next() {
+		Some(ch) if ch.is_whitespace() => Some(indent + keyword.chars().count()),
+		_ => None,
+	}
+}
+
+/// A statement of a script, which is a line together with
+/// the lines continuing it.
+pub struct Statement {
+	/// Number of the first line, counting from 1.
+	pub line: usize,
+	pub text: String,
+	/// If blank lines separate it from the previous statement.
+	pub after_blank_line: bool,
+}
+
+/// Nesting depth of block comments at the end of the line.
+fn comment_depth_after(line: &str, mut depth: usize) -> usize {
+	let chars: Vec<char> = line.chars().collect();
+	let mut index = 0;
+	while index < chars.len() {
+		let next = chars.get(index + 1).cloned();
+		match (chars[index], next) {
+			('{', Some('-')) => {
+				depth += 1;
+				index += 1;
+			},
+			('-', Some('}')) if depth > 0 => {
+				depth -= 1;
+				index += 1;
+			},
+			('-', Some('-')) | ('#', _) if depth == 0 => break,
+			_ => {},
+		}
+		index += 1;
+	}
+	depth
+}
+
+/// Splits a script into statements. A statement continues on the
+/// following lines if they are indented or inside a block comment,
+/// and blank lines end it.
+pub fn split_statements(source: &str) -> Vec<Statement> {
+	let mut statements: Vec<Statement> = Vec::new();
+	let mut depth = 0;
+	let mut continues = false;
+	let mut after_blank_line = false;
+	for (index, line) in source.lines().enumerate() {
+		let indented = line.starts_with(is_whitespace);
+		if depth == 0 && line.trim().is_empty() {
+			continues = false;
+			after_blank_line = true;
+			continue;
 		}
+		
+		if continues && (depth > 0 || indented) {
+			let last = statements.last_mut().unwrap();
+			last.text.push('\n');
+			last.text.push_str(line);
+		} else {
+			statements.push(Statement {
+				line: index + 1,
+				text: line.to_string(),
+				after_blank_line: after_blank_line && !statements.is_empty(),
+			});
+			after_blank_line = false;
+		}
+		continues = true;
+		depth = comment_depth_after(line, depth);
 	}
+	statements
 }
 
-fn create_church_numeral(num: u32) -> AstNode {
+pub fn create_church_numeral(num: u32) -> AstNode {
 	let mut node = AstNode::BoundVariable(0);
 	for _ in 0..num {
 		node = AstNode::Application(
@@ -249,111 +751,457 @@ fn create_church_numeral(num: u32) -> AstNode {
 	return AstNode::Function(Box::new(AstNode::Function(Box::new(node))));
 }
 
-fn map_optional_insert(map: &mut HashMap<char, u32>, key: char, value: Option<u32>) {
-	match value {
-		Some(val) => map.insert(key, val),
-		None => map.remove(&key),
-	};
+/// Stands in for a token missing from the source. Trees with
+/// errors are never handed out, so it doesn't need to be exact.
+fn missing_token(position: usize) -> SourceToken {
+	SourceToken {
+		leading: String::new(),
+		text: String::new(),
+		position,
+	}
 }
 
-fn parse_unit(parser: &mut Parser) -> Result<AstNode, ParseError> {
+fn parse_unit(parser: &mut Parser) -> Result<Cst, ParseError> {
+	match parser.peek()?.contents {
+		TokenContents::OpenParenth |
+		TokenContents::Number(..) |
+		TokenContents::Letter(..) |
+		TokenContents::Name(..) |
+		TokenContents::Operator => {},
+		_ => {
+			let error = parser.error("expected name, letter, number, or (".to_string());
+			let position = error.position;
+			parser.report(error);
+			// a stray dot is skipped, the callers deal with the rest
+			if let TokenContents::Dot = parser.peek()?.contents {
+				parser.consume()?;
+			}
+			return Ok(Cst::Name(missing_token(position)));
+		},
+	}
+	
 	let token = try!(parser.consume());
 	match token.contents {
 		TokenContents::OpenParenth => {
 			let node = try!(parse_node(parser));
-			let close_parenth = try!(parser.consume());
-			match close_parenth.contents {
-				TokenContents::CloseParenth => Ok(node),
-				_ => Err(parser.error("expected name, letter, number, (, or )".to_string())),
+			let error = parser.error("expected name, letter, number, (, or )".to_string());
+			match parser.peek()?.contents {
+				TokenContents::CloseParenth => {},
+				TokenContents::End => parser.report(
+					error.suggest("unbalanced `(` opened here", Some(token.position))),
+				TokenContents::Lambda => parser.report(
+					error.suggest("put the function in parentheses", None)),
+				_ => parser.report(error),
 			}
+			parser.synchronize()?;
+			let close_parenth = parser.consume()?;
+			Ok(Cst::Parenthesized(token.source(), Box::new(node), close_parenth.source()))
 		},
 		TokenContents::Number(num) => {
-			Ok(create_church_numeral(num))
+			Ok(Cst::Integer(token.source(), num))
 		},
-		TokenContents::Letter(ch) => {
-			match parser.bind_depths.get(&ch) {
-				Some(depth) => Ok(AstNode::BoundVariable(
-					parser.current_depth - depth)),
-				None => Ok(AstNode::FreeVariable(ch)),
-			}
-		},
-		TokenContents::Name(s) => {
-			Ok(AstNode::Name(s))
+		TokenContents::Letter(..) => {
+			Ok(Cst::Variable(token.source()))
 		},
 		_ => {
-			Err(parser.error("expected name, letter, number, or (".to_string()))
+			Ok(Cst::Name(token.source()))
 		},
 	}
 }
 
-fn parse_function(parser: &mut Parser) -> Result<AstNode, ParseError> {
-	let token = try!(parser.consume());
-	match token.contents {
-		TokenContents::Letter(ch) => {
-			parser.current_depth += 1;
-			let old = parser.bind_depths.insert(ch, parser.current_depth);
-			
-			let body;
-			match try!(parser.peek()).contents {
-				TokenContents::Dot => {
-					// we have just checked that this is 
-					// a dot token, so it can't be error
-					assert!(parser.consume().is_ok());
-					body = try!(parse_node(parser));
-				},
-				TokenContents::Letter(..) => {
-					body = try!(parse_function(parser));
-				},
-				_ => {
-					return Err(parser.error("expected letter or .".to_string()));
-				},
-			}
-			
-			parser.current_depth -= 1;
-			map_optional_insert(&mut parser.bind_depths, ch, old);
-			Ok(AstNode::Function(Box::new(body)))
-		},
-		_ => Err(parser.error("expected letter".to_string())),
+fn parse_function(parser: &mut Parser, lambda: Token) -> Result<Cst, ParseError> {
+	let mut params = Vec::new();
+	loop {
+		match parser.peek()?.contents {
+			TokenContents::Letter(..) => params.push(parser.consume()?.source()),
+			TokenContents::Dot => {
+				if params.is_empty() {
+					let error = parser.error("expected letter".to_string())
+						.suggest("add a parameter before `.`", None);
+					parser.report(error);
+				}
+				// we have just checked that this is 
+				// a dot token, so it can't be error
+				let dot = parser.consume()?;
+				let body = parse_node(parser)?;
+				return Ok(Cst::Function(lambda.source(), params, dot.source(), Box::new(body)));
+			},
+			TokenContents::Name(..) => {
+				let error = parser.error("expected letter".to_string())
+					.suggest("parameters are single lowercase letters", None);
+				parser.report(error);
+				params.push(parser.consume()?.source());
+			},
+			_ => {
+				let error = if params.is_empty() {
+					parser.error("expected letter".to_string())
+				} else {
+					parser.error("expected letter or .".to_string())
+						.suggest("missing `.` after binder list", None)
+				};
+				let position = error.position;
+				parser.report(error);
+				if parser.at_end()? {
+					return Ok(Cst::Name(missing_token(position)));
+				}
+				// carry on as if the dot was there
+				let body = parse_node(parser)?;
+				return Ok(Cst::Function(lambda.source(), params, missing_token(position), Box::new(body)));
+			},
+		}
 	}
 }
 
-fn parse_node(parser: &mut Parser) -> Result<AstNode, ParseError> {
-	match try!(parser.peek()).contents {
-		TokenContents::Lambda => {
-			// we have just checked that this is 
-			// a dot token, so it can't be error
-			assert!(parser.consume().is_ok());
-			return parse_function(parser);
-		}
-		_ => (),
+fn parse_node(parser: &mut Parser) -> Result<Cst, ParseError> {
+	if let TokenContents::Lambda = parser.peek()?.contents {
+		// we have just checked that this is
+		// a lambda token, so it can't be error
+		let lambda = parser.consume()?;
+		return parse_function(parser, lambda);
 	}
 	
-	let mut result = try!(parse_unit(parser));
+	let mut items = vec![parse_unit(parser)?];
 	
 	loop {
 		match try!(parser.peek()).contents {
 			TokenContents::OpenParenth |
 			TokenContents::Letter(..) |
 			TokenContents::Number(..) |
-			TokenContents::Name(..) => {
-				let next_unit = try!(parse_unit(parser));
-				result = AstNode::Application(
-					Box::new(result),
-					Box::new(next_unit));
+			TokenContents::Name(..) |
+			TokenContents::Operator => {
+				items.push(parse_unit(parser)?);
 			},
 			_ => break, 
 		}
 	}
-			
-	Ok(result)
+	
+	if items.len() == 1 {
+		Ok(items.pop().unwrap())
+	} else {
+		Ok(Cst::Application(items))
+	}
+}
+
+fn parse_whole(parser: &mut Parser) -> Result<SyntaxTree, ParseError> {
+	let node = parse_node(parser)?;
+	
+	// report what follows the term, and parse it for more errors
+	loop {
+		let error = parser.error("expected end of input".to_string());
+		match parser.peek()?.contents {
+			TokenContents::End => break,
+			TokenContents::CloseParenth => {
+				parser.report(error.suggest("unbalanced `)`, remove it or add a matching `(`", None));
+				parser.consume()?;
+			},
+			TokenContents::Lambda => {
+				parser.report(error.suggest("put the function in parentheses", None));
+			},
+			_ => {
+				parser.report(error);
+				parser.consume()?;
+			},
+		}
+		if !parser.at_end()? {
+			parse_node(parser)?;
+		}
+	}
+	
+	let end = parser.consume()?;
+	Ok(SyntaxTree {
+		term: node,
+		trailing: end.leading,
+	})
+}
+
+/// Parses a lambda term keeping its concrete syntax, reporting every
+/// syntax error. After an error parsing carries on from the next token,
+/// skipping to the closing parenthesis if the error is inside one.
+/// Errors are ordered by position.
+pub fn parse_tree_all(source: &str) -> Result<SyntaxTree, Vec<ParseError>> {
+	let mut parser = Parser::new(source);
+	parser.recover = true;
+	let result = parse_whole(&mut parser);
+	let mut errors = std::mem::take(&mut parser.errors);
+	match result {
+		Ok(tree) => {
+			if errors.is_empty() {
+				return Ok(tree);
+			}
+		},
+		Err(error) => errors.push(error),
+	}
+	errors.sort_by_key(|error| error.position);
+	Err(errors)
+}
+
+/// Parses a lambda term keeping its concrete syntax.
+pub fn parse_tree(source: &str) -> Result<SyntaxTree, ParseError> {
+	parse_tree_all(source).map_err(|errors| errors.into_iter().next().unwrap())
 }
 
 pub fn parse_object(source: &str) -> Result<AstNode, ParseError> {
-	let mut parser = Parser::new(source);	
-	let node = try!(parse_node(&mut parser));
+	parse_tree(source).map(|tree| tree.to_ast())
+}
+
+fn s_combinator() -> AstNode {
+	// \xyz.xz(yz)
+	let body = AstNode::Application(
+		Box::new(AstNode::Application(
+			Box::new(AstNode::BoundVariable(2)),
+			Box::new(AstNode::BoundVariable(0)))),
+		Box::new(AstNode::Application(
+			Box::new(AstNode::BoundVariable(1)),
+			Box::new(AstNode::BoundVariable(0)))));
+	AstNode::Function(Box::new(AstNode::Function(Box::new(
+		AstNode::Function(Box::new(body))))))
+}
+
+fn k_combinator() -> AstNode {
+	// \xy.x
+	AstNode::Function(Box::new(AstNode::Function(
+		Box::new(AstNode::BoundVariable(1)))))
+}
+
+fn i_combinator() -> AstNode {
+	// \x.x
+	AstNode::Function(Box::new(AstNode::BoundVariable(0)))
+}
+
+/// Parses a parenthesized term, a variable or a name. A name made only
+/// of `S`, `K` and `I` stands for each of those combinators in turn,
+/// so `SKK` is the same as `S K K`.
+fn parse_combinator_units(parser: &mut Parser) -> Result<Vec<AstNode>, ParseError> {
+	let token = parser.consume()?;
+	match token.contents {
+		TokenContents::OpenParenth => {
+			let node = parse_combinator_node(parser)?;
+			let close_parenth = parser.consume()?;
+			match close_parenth.contents {
+				TokenContents::CloseParenth => Ok(vec![node]),
+				_ => Err(parser.error("expected name, letter, (, or )".to_string())),
+			}
+		},
+		TokenContents::Letter(ch) => Ok(vec![AstNode::FreeVariable(ch)]),
+		TokenContents::Name(s) => {
+			if s.chars().all(|ch| ch == 'S' || ch == 'K' || ch == 'I') {
+				Ok(s.chars().map(|ch| match ch {
+					'S' => s_combinator(),
+					'K' => k_combinator(),
+					_ => i_combinator(),
+				}).collect())
+			} else {
+				Ok(vec![AstNode::Name(s)])
+			}
+		},
+		_ => Err(parser.error("expected name, letter, or (".to_string())),
+	}
+}
+
+fn parse_combinator_node(parser: &mut Parser) -> Result<AstNode, ParseError> {
+	let mut units = parse_combinator_units(parser)?;
+	while let TokenContents::OpenParenth | TokenContents::Letter(..) | TokenContents::Name(..) =
+		parser.peek()?.contents
+	{
+		units.extend(parse_combinator_units(parser)?);
+	}
 	
-	match try!(parser.peek()).contents {
+	let mut units = units.into_iter();
+	let first = units.next().unwrap();
+	Ok(units.fold(first, |result, unit| AstNode::Application(Box::new(result), Box::new(unit))))
+}
+
+/// Parses combinatory logic: `S`, `K` and `I` are translated to their
+/// lambda terms, also when written together like `SKK`, other names
+/// refer to previously bound definitions.
+pub fn parse_combinators(source: &str) -> Result<AstNode, ParseError> {
+	let mut parser = Parser::new(source);
+	let node = parse_combinator_node(&mut parser)?;
+	
+	match parser.peek()?.contents {
 		TokenContents::End => Ok(node),
 		_ => Err(parser.error("expected end of input".to_string())),
 	}
 }
+
+fn parse_iota_node(lexer: &mut Lexer) -> Result<AstNode, ParseError> {
+	lexer.skip_whitespace()?;
+	match lexer.peek_char() {
+		Some('i') => {
+			lexer.advance();
+			// i = \x.x S K
+			Ok(AstNode::Function(Box::new(AstNode::Application(
+				Box::new(AstNode::Application(
+					Box::new(AstNode::BoundVariable(0)),
+					Box::new(s_combinator()))),
+				Box::new(k_combinator())))))
+		},
+		Some('*') => {
+			lexer.advance();
+			let function = parse_iota_node(lexer)?;
+			let argument = parse_iota_node(lexer)?;
+			Ok(AstNode::Application(Box::new(function), Box::new(argument)))
+		},
+		Some(..) => Err(ParseError::new(lexer.position, "expected i or *".to_string())),
+		None => Err(ParseError::new(lexer.position, "unexpected end of input".to_string())),
+	}
+}
+
+pub fn parse_iota(source: &str) -> Result<AstNode, ParseError> {
+	let mut lexer = Lexer::new(source);
+	let node = parse_iota_node(&mut lexer)?;
+	lexer.skip_whitespace()?;
+	match lexer.peek_char() {
+		None => Ok(node),
+		Some(..) => Err(ParseError::new(lexer.position, "expected end of input".to_string())),
+	}
+}
+
+pub fn parse_jot(source: &str) -> Result<AstNode, ParseError> {
+	let mut lexer = Lexer::new(source);
+	let mut node = i_combinator();
+	loop {
+		lexer.skip_whitespace()?;
+		match lexer.peek_char() {
+			// [F0] = [F] S K
+			Some('0') => {
+				node = AstNode::Application(
+					Box::new(AstNode::Application(
+						Box::new(node),
+						Box::new(s_combinator()))),
+					Box::new(k_combinator()));
+			},
+			// [F1] = \xy.[F](xy), [F] is always closed
+			Some('1') => {
+				node = AstNode::Function(Box::new(AstNode::Function(
+					Box::new(AstNode::Application(
+						Box::new(node),
+						Box::new(AstNode::Application(
+							Box::new(AstNode::BoundVariable(1)),
+							Box::new(AstNode::BoundVariable(0)))))))));
+			},
+			Some(..) => return Err(ParseError::new(lexer.position, "expected 0 or 1".to_string())),
+			None => return Ok(node),
+		}
+		lexer.advance();
+	}
+}
+
+/// Parses the source from the character at `start`. Lambda terms get
+/// spans, see `AstNode::Spanned`, while other syntaxes parse as usual.
+/// Every syntax error of lambda terms is reported, see `parse_tree_all`.
+/// Error positions are relative to the whole source.
+pub fn parse_spanned(source: &Rc<Source>, start: usize, syntax: Syntax)
+	-> Result<AstNode, Vec<ParseError>>
+{
+	parse_range(source, start, source.text.chars().count(), syntax)
+}
+
+/// Like `parse_spanned`, for characters `start..end` of the source.
+pub fn parse_range(source: &Rc<Source>, start: usize, end: usize, syntax: Syntax)
+	-> Result<AstNode, Vec<ParseError>>
+{
+	let text: String = source.text.chars().skip(start).take(end - start).collect();
+	let result = match syntax {
+		Syntax::Lambda => parse_tree_all(&text).map(|tree| tree.to_spanned_ast(source, start)),
+		_ => parse(&text, syntax).map_err(|error| vec![error]),
+	};
+	result.map_err(|errors| errors.into_iter().map(|mut error| {
+		error.position += start;
+		if let Some(ref mut related) = error.related {
+			*related += start;
+		}
+		error
+	}).collect())
+}
+
+pub fn parse(source: &str, syntax: Syntax) -> Result<AstNode, ParseError> {
+	match syntax {
+		Syntax::Lambda => parse_object(source),
+		Syntax::Combinators => parse_combinators(source),
+		Syntax::Iota => parse_iota(source),
+		Syntax::Jot => parse_jot(source),
+		Syntax::Blc => blc::parse_bits(source),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use reduction::alpha_equivalent;
+
+	/// Text of each token of the source, with whitespace and
+	/// comments before it in brackets if there are any.
+	fn tokens(source: &str) -> Result<Vec<String>, ParseError> {
+		let mut lexer = Lexer::new(source);
+		let mut tokens = Vec::new();
+		loop {
+			let token = lexer.next_token()?;
+			let leading = token.leading.trim();
+			if !leading.is_empty() {
+				tokens.push(format!("[{}]", leading));
+			}
+			match token.contents {
+				TokenContents::End => return Ok(tokens),
+				_ => tokens.push(token.text),
+			}
+		}
+	}
+
+	#[test]
+	fn minus_and_comments() {
+		assert_eq!(tokens("- - a").ok().unwrap(), ["-", "-", "a"]);
+		assert_eq!(tokens("a --b").ok().unwrap(), ["a", "[--b]"]);
+		assert_eq!(tokens("a--b\nc").ok().unwrap(), ["a", "[--b]", "c"]);
+		assert_eq!(tokens("- -- minus").ok().unwrap(), ["-", "[-- minus]"]);
+		assert_eq!(tokens("-{- minus -}1").ok().unwrap(), ["-", "[{- minus -}]", "1"]);
+		assert_eq!(tokens("{- {- nested -} -} - 1").ok().unwrap(), ["[{- {- nested -} -}]", "-", "1"]);
+	}
+
+	#[test]
+	fn unterminated_block_comments() {
+		let error = tokens("a {- b -").err().unwrap();
+		assert_eq!(error.message, "unterminated block comment");
+		assert_eq!(error.position, 2);
+		assert_eq!(tokens("a -}").err().unwrap().message, "invalid token");
+	}
+
+	fn combinators(source: &str) -> AstNode {
+		parse_combinators(source).ok().unwrap()
+	}
+
+	#[test]
+	fn combinator_runs_are_split() {
+		assert!(alpha_equivalent(&combinators("SKK"), &combinators("S K K")));
+		assert!(alpha_equivalent(&combinators("S(KS)K x"), &combinators("S (K S) K x")));
+		// the run is applied to `x` one combinator at a time
+		assert!(alpha_equivalent(&combinators("x SKI"), &combinators("((x S) K) I")));
+		assert!(!alpha_equivalent(&combinators("x SKI"), &combinators("x (S K I)")));
+	}
+
+	#[test]
+	fn lambda_shorthands() {
+		let curried = parse("\\x y.x", Syntax::Lambda).ok().unwrap();
+		assert!(alpha_equivalent(&curried, &parse("\\a.\\b.a", Syntax::Lambda).ok().unwrap()));
+	}
+
+	#[test]
+	fn every_syntax_error_is_reported() {
+		let errors = match parse_tree_all("(\\.x) (y z") {
+			Ok(_) => panic!("broken term was parsed"),
+			Err(errors) => errors,
+		};
+		assert_eq!(errors.len(), 2);
+		assert!(errors[0].position < errors[1].position);
+		// the parenthesis that isn't closed
+		assert_eq!(errors[1].related, Some(6));
+	}
+
+	#[test]
+	fn other_names_are_kept() {
+		match combinators("SKIM") {
+			AstNode::Name(ref name) if name == "SKIM" => {},
+			_ => panic!("SKIM wasn't parsed as a name"),
+		}
+	}
+}
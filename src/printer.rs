@@ -0,0 +1,446 @@
+use std::char;
+use std::fmt;
+use AstNode;
+use native::Builtin;
+
+/// Writes the pieces of a term in some notation. The printer decides
+/// the structure and the parentheses, renderers only spell things out.
+/// Parameters are named `a`, `b`, ... by their depth.
+pub trait Renderer {
+	/// Everything before the body of a lambda, like `\a.`
+	fn lambda(&self, param: char, out: &mut String);
+	/// `index` is the de Bruijn index of the variable.
+	fn bound(&self, name: char, index: u32, out: &mut String);
+	/// Separator between a function and its argument.
+	fn application(&self, out: &mut String);
+
+	fn free(&self, name: char, out: &mut String) {
+		out.push(name);
+	}
+
+	fn name(&self, name: &str, out: &mut String) {
+		out.push_str(name);
+	}
+
+	fn integer(&self, num: u32, out: &mut String) {
+		out.push_str(&num.to_string());
+	}
+
+	fn builtin(&self, builtin: Builtin, out: &mut String) {
+		out.push_str(builtin.symbol());
+	}
+}
+
+/// The notation the parser reads: `\a.a b`.
+pub struct Ascii;
+
+/// `λa. a b`
+pub struct Unicode;
+
+/// `\lambda a.\, a\; b`, for math mode. With `subscripts` bound
+/// variables also show their de Bruijn indices: `a_{0}`.
+pub struct Latex {
+	pub subscripts: bool,
+}
+
+impl Renderer for Ascii {
+	fn lambda(&self, param: char, out: &mut String) {
+		out.push('\\');
+		out.push(param);
+		out.push('.');
+	}
+
+	fn bound(&self, name: char, _index: u32, out: &mut String) {
+		out.push(name);
+	}
+
+	fn application(&self, out: &mut String) {
+		out.push(' ');
+	}
+}
+
+impl Renderer for Unicode {
+	fn lambda(&self, param: char, out: &mut String) {
+		out.push('λ');
+		out.push(param);
+		out.push_str(". ");
+	}
+
+	fn bound(&self, name: char, _index: u32, out: &mut String) {
+		out.push(name);
+	}
+
+	fn application(&self, out: &mut String) {
+		out.push(' ');
+	}
+
+	fn builtin(&self, builtin: Builtin, out: &mut String) {
+		out.push_str(match builtin {
+			Builtin::Mul => "×",
+			Builtin::Sub => "−",
+			Builtin::Eq => "=",
+			Builtin::Leq => "≤",
+			_ => builtin.symbol(),
+		});
+	}
+}
+
+fn latex_escape(text: &str, out: &mut String) {
+	for ch in text.chars() {
+		match ch {
+			'_' | '#' | '%' | '&' | '{' | '}' | '$' => {
+				out.push('\\');
+				out.push(ch);
+			},
+			'\\' => out.push_str("\\backslash{}"),
+			'^' => out.push_str("\\hat{}"),
+			'~' => out.push_str("\\sim{}"),
+			ch => out.push(ch),
+		}
+	}
+}
+
+impl Renderer for Latex {
+	fn lambda(&self, param: char, out: &mut String) {
+		out.push_str("\\lambda ");
+		out.push(param);
+		out.push_str(".\\, ");
+	}
+
+	fn bound(&self, name: char, index: u32, out: &mut String) {
+		out.push(name);
+		if self.subscripts {
+			out.push_str(&format!("_{{{}}}", index));
+		}
+	}
+
+	fn application(&self, out: &mut String) {
+		out.push_str("\\; ");
+	}
+
+	fn free(&self, name: char, out: &mut String) {
+		latex_escape(&name.to_string(), out);
+	}
+
+	fn name(&self, name: &str, out: &mut String) {
+		out.push_str("\\mathsf{");
+		latex_escape(name, out);
+		out.push('}');
+	}
+
+	fn builtin(&self, builtin: Builtin, out: &mut String) {
+		out.push_str(match builtin {
+			Builtin::Add => "+",
+			Builtin::Mul => "\\times",
+			Builtin::Sub => "-",
+			Builtin::Eq => "=",
+			Builtin::Leq => "\\leq",
+		});
+	}
+}
+
+/// Notations the interpreter can print results in.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Style {
+	Ascii,
+	Unicode,
+	Latex,
+	/// LaTeX with de Bruijn subscripts.
+	LatexIndices,
+}
+
+static LATEX: Latex = Latex { subscripts: false };
+static LATEX_INDICES: Latex = Latex { subscripts: true };
+
+impl Style {
+	pub fn from_name(name: &str) -> Option<Style> {
+		match name {
+			"ascii" => Some(Style::Ascii),
+			"unicode" => Some(Style::Unicode),
+			"latex" => Some(Style::Latex),
+			"latex-indices" => Some(Style::LatexIndices),
+			_ => None,
+		}
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Style::Ascii => "ascii",
+			Style::Unicode => "unicode",
+			Style::Latex => "latex",
+			Style::LatexIndices => "latex-indices",
+		}
+	}
+
+	pub fn renderer(self) -> &'static dyn Renderer {
+		match self {
+			Style::Ascii => &Ascii,
+			Style::Unicode => &Unicode,
+			Style::Latex => &LATEX,
+			Style::LatexIndices => &LATEX_INDICES,
+		}
+	}
+}
+
+/// Layout document, as in Wadler's "A prettier printer". A group is
+/// printed on one line if it fits, otherwise its lines are broken.
+enum Doc {
+	Text(String),
+	/// Line break, printed as the given separator when not broken.
+	Line(String),
+	Nest(usize, Box<Doc>),
+	Group(Box<Doc>),
+	Concat(Vec<Doc>),
+}
+
+/// Indentation of broken lines, relative to the enclosing term.
+const INDENT: usize = 2;
+
+fn piece<F: Fn(&mut String)>(write: F) -> String {
+	let mut out = String::new();
+	write(&mut out);
+	out
+}
+
+fn parenthesized(doc: Doc) -> Doc {
+	Doc::Concat(vec![Doc::Text("(".to_string()), doc, Doc::Text(")".to_string())])
+}
+
+fn render_walk(node: &AstNode, current_depth: u32, in_application: bool,
+               renderer: &dyn Renderer) -> Doc {
+	let node = node.unspanned();
+	match node {
+		&AstNode::Application(..) => {
+			// f a b c is laid out as a whole, breaking before arguments
+			let mut args = Vec::new();
+			let mut head = node;
+			while let AstNode::Application(a, b) = head {
+				args.push(b.unspanned());
+				head = a.unspanned();
+			}
+			
+			let mut rest = Vec::new();
+			for arg in args.into_iter().rev() {
+				rest.push(Doc::Line(piece(|out| renderer.application(out))));
+				rest.push(match arg {
+					&AstNode::Application(..) =>
+						parenthesized(render_walk(arg, current_depth, true, renderer)),
+					_ =>
+						render_walk(arg, current_depth, true, renderer),
+				});
+			}
+			Doc::Group(Box::new(Doc::Concat(vec![
+				render_walk(head, current_depth, true, renderer),
+				Doc::Nest(INDENT, Box::new(Doc::Concat(rest))),
+			])))
+		},
+		&AstNode::Function(..) => {
+			// \a.\b.body, breaking only before the body
+			let mut lambdas = String::new();
+			let mut depth = current_depth;
+			let mut body = node;
+			while let AstNode::Function(inner) = body {
+				let param = char::from_u32(depth + ('a' as u32))
+					.unwrap_or('?');
+				renderer.lambda(param, &mut lambdas);
+				depth += 1;
+				body = inner.unspanned();
+			}
+			
+			let doc = Doc::Group(Box::new(Doc::Concat(vec![
+				Doc::Text(lambdas),
+				Doc::Nest(INDENT, Box::new(Doc::Concat(vec![
+					Doc::Line(String::new()),
+					render_walk(body, depth, false, renderer),
+				]))),
+			])));
+			if in_application { parenthesized(doc) } else { doc }
+		},
+		&AstNode::BoundVariable(num) => {
+			let ch = char::from_u32(
+				current_depth - num - 1 + ('a' as u32));
+			Doc::Text(piece(|out| renderer.bound(ch.unwrap_or('?'), num, out)))
+		},
+		&AstNode::FreeVariable(ch) =>
+			Doc::Text(piece(|out| renderer.free(ch, out))),
+		AstNode::Name(name) =>
+			Doc::Text(piece(|out| renderer.name(name, out))),
+		&AstNode::Integer(num) =>
+			Doc::Text(piece(|out| renderer.integer(num, out))),
+		&AstNode::Builtin(builtin) =>
+			Doc::Text(piece(|out| renderer.builtin(builtin, out))),
+		AstNode::Foreign(primitive) =>
+			Doc::Text(piece(|out| renderer.name(&primitive.name, out))),
+		&AstNode::Spanned(..) |
+		&AstNode::Origin(..) => unreachable!(),
+	}
+}
+
+/// Checks if the document fits into the remaining width when
+/// printed flat, up to the next line break of what follows it.
+fn fits(mut remaining: isize, doc: &Doc, rest: &[(usize, bool, &Doc)]) -> bool {
+	let mut stack = vec![(0, true, doc)];
+	let mut rest = rest.iter().rev();
+	loop {
+		if remaining < 0 {
+			return false;
+		}
+		let (indent, flat, doc) = match stack.pop() {
+			Some(item) => item,
+			None => match rest.next() {
+				Some(&item) => item,
+				None => return true,
+			},
+		};
+		match doc {
+			Doc::Text(text) => remaining -= text.chars().count() as isize,
+			Doc::Line(separator) if flat => remaining -= separator.chars().count() as isize,
+			&Doc::Line(..) => return true,
+			&Doc::Nest(by, ref inner) => stack.push((indent + by, flat, &**inner)),
+			Doc::Group(inner) => stack.push((indent, flat, &**inner)),
+			Doc::Concat(docs) => {
+				for inner in docs.iter().rev() {
+					stack.push((indent, flat, inner));
+				}
+			},
+		}
+	}
+}
+
+fn layout(doc: &Doc, width: Option<usize>) -> String {
+	let mut out = String::new();
+	let mut column = 0;
+	let mut stack = vec![(0, width.is_none(), doc)];
+	while let Some((indent, flat, doc)) = stack.pop() {
+		match doc {
+			Doc::Text(text) => {
+				out.push_str(text);
+				column += text.chars().count();
+			},
+			Doc::Line(separator) if flat => {
+				out.push_str(separator);
+				column += separator.chars().count();
+			},
+			Doc::Line(separator) => {
+				// keep visible separators, like \; in LaTeX
+				let trimmed = out.trim_end().len();
+				out.truncate(trimmed);
+				out.push_str(separator.trim_end());
+				out.push('\n');
+				for _ in 0..indent {
+					out.push(' ');
+				}
+				column = indent;
+			},
+			&Doc::Nest(by, ref inner) => stack.push((indent + by, flat, &**inner)),
+			Doc::Group(inner) => {
+				let remaining = width.unwrap_or(0) as isize - column as isize;
+				let flat = flat || fits(remaining, inner, &stack);
+				stack.push((indent, flat, &**inner));
+			},
+			Doc::Concat(docs) => {
+				for inner in docs.iter().rev() {
+					stack.push((indent, flat, inner));
+				}
+			},
+		}
+	}
+	out
+}
+
+/// Formats the term on one line with as few parentheses as the
+/// notation allows.
+pub fn render(node: &AstNode, renderer: &dyn Renderer) -> String {
+	layout(&render_walk(node, 0, false, renderer), None)
+}
+
+/// Like `render`, for a subterm under the given number of binders.
+/// Variables are named as they are in the whole term.
+pub fn render_under(node: &AstNode, depth: u32, renderer: &dyn Renderer) -> String {
+	layout(&render_walk(node, depth, false, renderer), None)
+}
+
+/// Like `render`, but breaks long applications and lambda bodies
+/// into indented lines to keep within the width where possible.
+pub fn render_width(node: &AstNode, renderer: &dyn Renderer, width: usize) -> String {
+	layout(&render_walk(node, 0, false, renderer), Some(width))
+}
+
+/// Displays the term in the given style, for use with `format!`.
+/// Lines are broken at `width` if it is set.
+pub struct Pretty<'a> {
+	pub node: &'a AstNode,
+	pub style: Style,
+	pub width: Option<usize>,
+}
+
+impl<'a> fmt::Display for Pretty<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let doc = render_walk(self.node, 0, false, self.style.renderer());
+		write!(f, "{}", layout(&doc, self.width))
+	}
+}
+
+/// Displays the term in the ASCII notation.
+impl fmt::Display for AstNode {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", render(self, &Ascii))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use parser::{self, Syntax};
+
+	fn term(source: &str) -> AstNode {
+		parser::parse(source, Syntax::Lambda).ok().unwrap()
+	}
+
+	fn styled(node: &AstNode, style: Style) -> String {
+		render(node, style.renderer())
+	}
+
+	#[test]
+	fn styles() {
+		let node = term("\\x.\\y.x (y x) FOO");
+		assert_eq!(styled(&node, Style::Ascii), "\\a.\\b.a (b a) FOO");
+		assert_eq!(styled(&node, Style::Unicode), "λa. λb. a (b a) FOO");
+		assert_eq!(styled(&node, Style::Latex),
+			"\\lambda a.\\, \\lambda b.\\, a\\; (b\\; a)\\; \\mathsf{FOO}");
+		assert_eq!(styled(&node, Style::LatexIndices),
+			"\\lambda a.\\, \\lambda b.\\, a_{1}\\; (b_{0}\\; a_{1})\\; \\mathsf{FOO}");
+	}
+
+	#[test]
+	fn builtins_and_parentheses() {
+		let comparison = AstNode::Application(
+			Box::new(AstNode::Application(
+				Box::new(AstNode::Builtin(Builtin::Leq)),
+				Box::new(AstNode::Integer(2)))),
+			Box::new(term("\\x.x")));
+		assert_eq!(styled(&comparison, Style::Ascii), "<= 2 (\\a.a)");
+		assert_eq!(styled(&comparison, Style::Unicode), "≤ 2 (λa. a)");
+		assert_eq!(styled(&comparison, Style::Latex), "\\leq\\; 2\\; (\\lambda a.\\, a)");
+	}
+
+	#[test]
+	fn subterms_keep_their_names() {
+		let node = term("\\x.\\y.y x");
+		if let AstNode::Function(ref body) = node {
+			assert_eq!(render_under(body, 1, &Ascii), "\\b.b a");
+		}
+	}
+
+	#[test]
+	fn long_terms_are_broken() {
+		let node = term("ADD (MUL 2 3) (\\x.SUCC x)");
+		assert_eq!(render_width(&node, &Ascii, 80), "ADD (MUL 2 3) (\\a.SUCC a)");
+		assert_eq!(render_width(&node, &Ascii, 14), "ADD\n  (MUL 2 3)\n  (\\a.SUCC a)");
+		assert_eq!(render_width(&node, &Ascii, 8), "ADD\n  (MUL\n    2\n    3)\n  (\\a.\n    SUCC\n      a)");
+		assert_eq!(render_width(&node, &Latex { subscripts: false }, 40),
+			"\\mathsf{ADD}\\;\n  (\\mathsf{MUL}\\; 2\\; 3)\\;\n  (\\lambda a.\\, \\mathsf{SUCC}\\; a)");
+		let pretty = Pretty { node: &node, style: Style::Unicode, width: Some(14) };
+		assert_eq!(pretty.to_string(), "ADD\n  (MUL 2 3)\n  (λa. SUCC a)");
+	}
+}
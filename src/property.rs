@@ -0,0 +1,364 @@
+use std::fmt;
+use std::rc::Rc;
+use AstNode;
+use church::ToChurch;
+use diagnostic::{Diagnostic, Source, Span};
+use parser;
+
+/// Number of random cases a law is checked on.
+pub const CASES: usize = 100;
+
+/// Number of times the shrinker may evaluate the law.
+const SHRINK_LIMIT: usize = 200;
+
+/// Largest generated number.
+const MAX_SIZE: u32 = 10;
+
+/// Kinds of values that laws quantify over.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Sort {
+	Nat,
+	Bool,
+	List,
+}
+
+impl Sort {
+	fn from_name(name: &str) -> Option<Sort> {
+		match name {
+			"nat" => Some(Sort::Nat),
+			"bool" => Some(Sort::Bool),
+			"list" => Some(Sort::List),
+			_ => None,
+		}
+	}
+}
+
+/// Input of a law, given to it Church encoded.
+#[derive(Clone)]
+pub enum Value {
+	Nat(u32),
+	Bool(bool),
+	List(Vec<u32>),
+}
+
+impl Value {
+	fn to_church(&self) -> AstNode {
+		match self {
+			&Value::Nat(num) => num.to_church(),
+			&Value::Bool(value) => value.to_church(),
+			Value::List(items) => items.to_church(),
+		}
+	}
+
+	/// Smaller values to try in place of this one, simplest first.
+	fn shrink(&self) -> Vec<Value> {
+		match self {
+			&Value::Nat(0) => Vec::new(),
+			&Value::Nat(num) => {
+				let mut smaller = vec![Value::Nat(0)];
+				if num / 2 > 0 {
+					smaller.push(Value::Nat(num / 2));
+				}
+				if num - 1 > num / 2 {
+					smaller.push(Value::Nat(num - 1));
+				}
+				smaller
+			},
+			&Value::Bool(true) => vec![Value::Bool(false)],
+			&Value::Bool(false) => Vec::new(),
+			Value::List(items) => {
+				let mut smaller = Vec::new();
+				if !items.is_empty() {
+					smaller.push(Value::List(Vec::new()));
+				}
+				for index in 0..items.len() {
+					let mut removed = items.clone();
+					removed.remove(index);
+					smaller.push(Value::List(removed));
+				}
+				for index in 0..items.len() {
+					for item in Value::Nat(items[index]).shrink() {
+						if let Value::Nat(item) = item {
+							let mut changed = items.clone();
+							changed[index] = item;
+							smaller.push(Value::List(changed));
+						}
+					}
+				}
+				smaller
+			},
+		}
+	}
+}
+
+impl fmt::Display for Value {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			&Value::Nat(num) => write!(f, "{}", num),
+			&Value::Bool(true) => write!(f, "TRUE"),
+			&Value::Bool(false) => write!(f, "FALSE"),
+			Value::List(items) => {
+				let items: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+				write!(f, "[{}]", items.join(", "))
+			},
+		}
+	}
+}
+
+/// Xorshift generator, seeded the same way for every law so that
+/// failures can be reproduced.
+struct Random(u64);
+
+impl Random {
+	fn below(&mut self, bound: u32) -> u32 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		(self.0 % bound as u64) as u32
+	}
+
+	/// Value of the sort, no bigger than `size`.
+	fn value(&mut self, sort: Sort, size: u32) -> Value {
+		match sort {
+			Sort::Nat => Value::Nat(self.below(size + 1)),
+			Sort::Bool => Value::Bool(self.below(2) == 1),
+			Sort::List => {
+				// lists are kept short, and their items small, because
+				// folding them over Church numerals is slow
+				let length = self.below(size / 2 + 1);
+				Value::List((0..length).map(|_| self.below(size / 2 + 1)).collect())
+			},
+		}
+	}
+}
+
+/// A statement like `forall m n l:list. EQ (ADD m n) (ADD n m)`,
+/// which should reduce to `TRUE` for all values of the variables.
+/// Variables are numbers unless they say otherwise.
+pub struct Law {
+	pub variables: Vec<(char, Sort)>,
+	/// Position of the body in the statement.
+	pub body: usize,
+}
+
+impl fmt::Display for Law {
+	/// Writes the part before the body, like `forall m n l:list.`
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "forall")?;
+		for &(name, sort) in self.variables.iter() {
+			match sort {
+				Sort::Nat => write!(f, " {}", name)?,
+				Sort::Bool => write!(f, " {}:bool", name)?,
+				Sort::List => write!(f, " {}:list", name)?,
+			}
+		}
+		write!(f, ".")
+	}
+}
+
+/// Checks if the statement is a law, rather than a term.
+pub fn is_law(text: &str) -> bool {
+	parser::statement_keyword(text, "forall").is_some()
+}
+
+/// Parses the variables of a law, see `Law`.
+pub fn parse_law(source: &Rc<Source>) -> Result<Law, Diagnostic> {
+	let chars: Vec<char> = source.text.chars().collect();
+	let mut position = parser::statement_keyword(&source.text, "forall").expect("not a law");
+	let mut variables = Vec::new();
+	loop {
+		while position < chars.len() && chars[position].is_whitespace() {
+			position += 1;
+		}
+		if position < chars.len() && chars[position] == '.' {
+			break;
+		}
+		let start = position;
+		while position < chars.len() && !chars[position].is_whitespace() && chars[position] != '.' {
+			position += 1;
+		}
+		let span = Span::new(source, start, position);
+		if start == position {
+			return Err(Diagnostic::error("expected `.` after the variables of the law")
+				.at(Some(&span)));
+		}
+
+		let word: String = chars[start..position].iter().cloned().collect();
+		let (name, sort) = match word.find(':') {
+			Some(index) => (&word[..index], &word[index + 1..]),
+			None => (&word[..], "nat"),
+		};
+		let mut letters = name.chars();
+		let name = match (letters.next(), letters.next()) {
+			(Some(ch @ 'a'..='z'), None) => ch,
+			_ => return Err(Diagnostic::error(format!("invalid variable: {}", name))
+				.at(Some(&span))
+				.help("variables are single lowercase letters")),
+		};
+		if variables.iter().any(|&(other, _)| other == name) {
+			return Err(Diagnostic::error(format!("variable {} is already bound", name))
+				.at(Some(&span)));
+		}
+		match Sort::from_name(sort) {
+			Some(sort) => variables.push((name, sort)),
+			None => return Err(Diagnostic::error(format!("unknown sort: {}", sort))
+				.at(Some(&span))
+				.help("expected nat, bool, or list")),
+		}
+	}
+
+	if variables.is_empty() {
+		let span = Span::new(source, position, position + 1);
+		return Err(Diagnostic::error("expected variables before `.`").at(Some(&span)));
+	}
+	Ok(Law {
+		variables,
+		body: position + 1,
+	})
+}
+
+/// Replaces the variables of the law with the values.
+pub fn instantiate(node: &AstNode, values: &[(char, Value)]) -> AstNode {
+	match node {
+		&AstNode::FreeVariable(ch) => match values.iter().find(|value| value.0 == ch) {
+			// values are closed terms, so they don't need shifting
+			Some(value) => value.1.to_church(),
+			None => node.clone(),
+		},
+		AstNode::Function(body) => AstNode::Function(Box::new(instantiate(body, values))),
+		AstNode::Application(a, b) =>
+			AstNode::Application(Box::new(instantiate(a, values)), Box::new(instantiate(b, values))),
+		_ => node.clone(),
+	}
+}
+
+/// Result of a law for one choice of values. A law fails if it
+/// doesn't reduce to `TRUE`, and the message says what happened.
+pub type Verdict = Result<(), String>;
+
+/// A choice of values the law fails on.
+pub struct Counterexample {
+	pub values: Vec<(char, Value)>,
+	pub message: String,
+	/// Number of the random case that failed, counting from 1.
+	pub case: usize,
+	/// Number of times the values were made smaller.
+	pub shrinks: usize,
+}
+
+/// Checks the law on `CASES` random choices of values, starting with
+/// small ones. The first failure is shrunk to a simplest one that still
+/// fails.
+pub fn check<F>(law: &Law, mut test: F) -> Result<(), Counterexample>
+	where F: FnMut(&[(char, Value)]) -> Verdict
+{
+	let mut random = Random(0x2545f4914f6cdd1d);
+	for case in 0..CASES {
+		let size = (case as u32 * (MAX_SIZE + 1) / CASES as u32).min(MAX_SIZE);
+		let values: Vec<(char, Value)> = law.variables.iter()
+			.map(|&(name, sort)| (name, random.value(sort, size)))
+			.collect();
+		if let Err(message) = test(&values) {
+			return Err(shrink(Counterexample {
+				values,
+				message,
+				case: case + 1,
+				shrinks: 0,
+			}, test));
+		}
+	}
+	Ok(())
+}
+
+fn shrink<F>(mut failure: Counterexample, mut test: F) -> Counterexample
+	where F: FnMut(&[(char, Value)]) -> Verdict
+{
+	let mut tries = 0;
+	'shrinking: while tries < SHRINK_LIMIT {
+		for index in 0..failure.values.len() {
+			for smaller in failure.values[index].1.shrink() {
+				let mut values = failure.values.clone();
+				values[index].1 = smaller;
+				tries += 1;
+				if let Err(message) = test(&values) {
+					failure.values = values;
+					failure.message = message;
+					failure.shrinks += 1;
+					continue 'shrinking;
+				}
+				if tries == SHRINK_LIMIT {
+					break 'shrinking;
+				}
+			}
+		}
+		// nothing smaller fails
+		break;
+	}
+	failure
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn law(text: &str) -> Law {
+		parse_law(&Source::new(text)).ok().unwrap()
+	}
+
+	fn nat(values: &[(char, Value)], index: usize) -> u32 {
+		match values[index].1 {
+			Value::Nat(num) => num,
+			_ => panic!("expected a number"),
+		}
+	}
+
+	fn shown(counterexample: &Counterexample) -> String {
+		let values: Vec<String> = counterexample.values.iter()
+			.map(|&(name, ref value)| format!("{} = {}", name, value))
+			.collect();
+		values.join(", ")
+	}
+
+	#[test]
+	fn laws_are_parsed() {
+		let parsed = law("forall m b:bool l:list. EQ m m");
+		assert_eq!(parsed.to_string(), "forall m b:bool l:list.");
+		assert_eq!(parsed.body, 23);
+		let error = |text| parse_law(&Source::new(text)).err().unwrap().message;
+		assert_eq!(error("forall xs. x"), "invalid variable: xs");
+		assert_eq!(error("forall m m. m"), "variable m is already bound");
+		assert_eq!(error("forall m:int. m"), "unknown sort: int");
+		assert_eq!(error("forall . TRUE"), "expected variables before `.`");
+		assert_eq!(error("forall m"), "expected `.` after the variables of the law");
+	}
+
+	#[test]
+	fn laws_that_hold_pass() {
+		let mut cases = 0;
+		assert!(check(&law("forall m n. TRUE"), |_| {
+			cases += 1;
+			Ok(())
+		}).is_ok());
+		assert_eq!(cases, CASES);
+	}
+
+	#[test]
+	fn numbers_shrink_to_the_smallest_failure() {
+		let failure = check(&law("forall m n. LT m 3"), |values| {
+			if nat(values, 0) >= 3 { Err("FALSE".to_string()) } else { Ok(()) }
+		}).err().unwrap();
+		assert_eq!(shown(&failure), "m = 3, n = 0");
+		assert_eq!(failure.message, "FALSE");
+		assert!(failure.shrinks > 0);
+	}
+
+	#[test]
+	fn lists_and_booleans_shrink() {
+		let failure = check(&law("forall b:bool l:list. b"), |values| match values {
+			[(_, Value::Bool(true)), (_, Value::List(items))] if items.iter().any(|&item| item >= 4) =>
+				Err("FALSE".to_string()),
+			_ => Ok(()),
+		}).err().unwrap();
+		assert_eq!(shown(&failure), "b = TRUE, l = [4]");
+	}
+}
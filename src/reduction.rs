@@ -1,6 +1,13 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 use AstNode;
+use foreign::{self, Primitive};
+use native;
+use parser;
+use types;
 
-fn copy_node(node: &AstNode) -> AstNode {
+pub fn copy_node(node: &AstNode) -> AstNode {
 	match node {
 		&AstNode::Application(ref a, ref b) =>
 			AstNode::Application(
@@ -12,8 +19,18 @@ fn copy_node(node: &AstNode) -> AstNode {
 			AstNode::BoundVariable(num),
 		&AstNode::Function(ref body) =>
 			AstNode::Function(Box::new(copy_node(&**body))),
+		&AstNode::Integer(num) =>
+			AstNode::Integer(num),
+		&AstNode::Builtin(builtin) =>
+			AstNode::Builtin(builtin),
+		AstNode::Foreign(primitive) =>
+			AstNode::Foreign(primitive.clone()),
 		&AstNode::Name(..) =>
 			panic!("name node in expression"),
+		&AstNode::Spanned(..) =>
+			panic!("spanned node in expression"),
+		AstNode::Origin(name, node) =>
+			AstNode::Origin(name.clone(), Box::new(copy_node(node))),
 	}
 }
 
@@ -29,24 +46,37 @@ fn increment_free(node: &AstNode, by: u32, free_threshold: u32) -> AstNode {
 		&AstNode::BoundVariable(num) =>
 			// bound variable, don't change
 			AstNode::BoundVariable(num),
-		&AstNode::FreeVariable(ch) =>
-			AstNode::FreeVariable(ch),
+		&AstNode::FreeVariable(..) |
+		&AstNode::Integer(..) |
+		&AstNode::Builtin(..) |
+		&AstNode::Foreign(..) =>
+			copy_node(node),
 		&AstNode::Function(ref body) =>
 			AstNode::Function(
 				Box::new(increment_free(&**body, by, free_threshold + 1))),
 		&AstNode::Name(..) =>
 			panic!("name node in expression"),
+		&AstNode::Spanned(..) =>
+			panic!("spanned node in expression"),
+		AstNode::Origin(name, node) =>
+			AstNode::Origin(name.clone(), Box::new(increment_free(node, by, free_threshold))),
 	}
 }
 
-fn substitute_walk(node: &AstNode, depth: u32, arg: &AstNode) -> AstNode {
+/// Substitutes `arg` for the variable bound at `depth`, counting
+/// the occurrences replaced in `uses`.
+fn substitute_walk(node: &AstNode, depth: u32, arg: &AstNode, uses: &mut usize) -> AstNode {
 	match node {
-		&AstNode::FreeVariable(ch) => 
-			AstNode::FreeVariable(ch),
+		&AstNode::FreeVariable(..) |
+		&AstNode::Integer(..) |
+		&AstNode::Builtin(..) |
+		&AstNode::Foreign(..) =>
+			copy_node(node),
 		&AstNode::BoundVariable(num) if num == depth => {
 			// this variable is bound by the parameter
 			// of function that's body we are working on,
 			// increment free variables in arg and return
+			*uses += 1;
 			increment_free(arg, depth, 0)
 		},
 		&AstNode::BoundVariable(num) if num > depth => {
@@ -58,55 +88,547 @@ fn substitute_walk(node: &AstNode, depth: u32, arg: &AstNode) -> AstNode {
 			AstNode::BoundVariable(num),
 		&AstNode::Function(ref body) =>
 			AstNode::Function(
-				Box::new(substitute_walk(&**body, depth + 1, arg))),
-		&AstNode::Application(ref a, ref b) =>
+				Box::new(substitute_walk(body, depth + 1, arg, uses))),
+		AstNode::Application(a, b) =>
 			AstNode::Application(
-				Box::new(substitute_walk(&**a, depth, arg)),
-				Box::new(substitute_walk(&**b, depth, arg))),
+				Box::new(substitute_walk(a, depth, arg, uses)),
+				Box::new(substitute_walk(b, depth, arg, uses))),
 		&AstNode::Name(..) =>
 			panic!("name node in expression"),
+		&AstNode::Spanned(..) =>
+			panic!("spanned node in expression"),
+		AstNode::Origin(name, node) =>
+			AstNode::Origin(name.clone(), Box::new(substitute_walk(node, depth, arg, uses))),
 	}
 }
 
 fn substitute(node: &AstNode, arg: &AstNode) -> AstNode {
-	substitute_walk(node, 0, arg)
+	substitute_walk(node, 0, arg, &mut 0)
 }
 
-fn reduce_application(left: &AstNode, right: &AstNode, to_fn: bool) -> AstNode {
-	let left_fn = reduce_node(left, true);
-	match left_fn {
+/// Removes the origins that were added for profiling.
+pub fn strip_origins(node: &AstNode) -> AstNode {
+	match node {
+		AstNode::Origin(_, node) => strip_origins(node),
+		AstNode::Function(body) => AstNode::Function(Box::new(strip_origins(body))),
+		AstNode::Application(a, b) =>
+			AstNode::Application(Box::new(strip_origins(a)), Box::new(strip_origins(b))),
+		_ => node.clone(),
+	}
+}
+
+/// Number of variables, functions and applications in the term.
+fn size(node: &AstNode) -> usize {
+	match node {
+		AstNode::Function(body) => size(body) + 1,
+		AstNode::Application(a, b) => size(a) + size(b) + 1,
+		AstNode::Origin(_, node) => size(node),
+		_ => 1,
+	}
+}
+
+/// Name of the native or foreign function at the head of the
+/// applications, which a delta step is attributed to.
+fn primitive_name(node: &AstNode) -> Option<Rc<str>> {
+	match node {
+		AstNode::Application(function, _) => primitive_name(function),
+		&AstNode::Builtin(builtin) => Some(Rc::from(builtin.symbol())),
+		AstNode::Foreign(primitive) => Some(Rc::from(&*primitive.name)),
+		_ => None,
+	}
+}
+
+/// Returns the terms obtained by contracting one beta redex of the
+/// node, for each redex in leftmost outermost order. The first one
+/// is the step normal order reduction would take.
+pub fn single_steps(node: &AstNode) -> Vec<AstNode> {
+	match node {
+		AstNode::Application(a, b) => {
+			let mut result = Vec::new();
+			if let Some(reduct) = contract(node) {
+				result.push(reduct);
+			}
+			for reduct in single_steps(a) {
+				result.push(AstNode::Application(Box::new(reduct), Box::new(copy_node(b))));
+			}
+			for reduct in single_steps(b) {
+				result.push(AstNode::Application(Box::new(copy_node(a)), Box::new(reduct)));
+			}
+			result
+		},
 		AstNode::Function(body) =>
-			reduce_node(&substitute(&body, right), to_fn),
-		AstNode::Name(..) =>
-			panic!("name node in expression"),
-		_ => 
-			AstNode::Application(
-				Box::new(reduce_node(&left_fn, false)),
-				Box::new(reduce_node(right, false)))
+			single_steps(body).into_iter()
+				.map(|reduct| AstNode::Function(Box::new(reduct)))
+				.collect(),
+		AstNode::Origin(name, node) =>
+			single_steps(node).into_iter()
+				.map(|reduct| AstNode::Origin(name.clone(), Box::new(reduct)))
+				.collect(),
+		_ => Vec::new(),
 	}
 }
 
-fn reduce_node(node: &AstNode, to_fn: bool) -> AstNode {
+/// Contracts the term if it is a beta redex. The function may
+/// be tagged with its origin.
+pub fn contract(node: &AstNode) -> Option<AstNode> {
+	if let AstNode::Application(a, b) = node {
+		let mut function = &**a;
+		while let AstNode::Origin(_, node) = function {
+			function = &**node;
+		}
+		if let AstNode::Function(body) = function {
+			return Some(substitute(body, b));
+		}
+	}
+	None
+}
+
+/// Checks if the variable with the de Bruijn index occurs in the node.
+pub fn uses_variable(node: &AstNode, index: u32) -> bool {
+	match node {
+		&AstNode::BoundVariable(num) => num == index,
+		AstNode::Function(body) => uses_variable(body, index + 1),
+		AstNode::Application(a, b) => uses_variable(a, index) || uses_variable(b, index),
+		&AstNode::Spanned(_, ref node) |
+		&AstNode::Origin(_, ref node) => uses_variable(node, index),
+		_ => false,
+	}
+}
+
+/// Contracts every eta redex `\x.f x`, where `x` is not used
+/// in `f`, to `f`.
+pub fn eta_reduce(node: &AstNode) -> AstNode {
 	match node {
-		&AstNode::Function(ref body) => {
-			if to_fn {
-				// already a function, just return copy
-				copy_node(node)
-			} else {
-				// reduce recursively
-				AstNode::Function(Box::new(reduce_node(&**body, false)))
+		AstNode::Function(body) => {
+			let body = eta_reduce(body);
+			if let AstNode::Application(ref f, ref x) = body {
+				if let AstNode::BoundVariable(0) = **x {
+					if !uses_variable(f, 0) {
+						// f is moved out of the function, which only
+						// decrements its free variables
+						return substitute(f, &AstNode::BoundVariable(0));
+					}
+				}
 			}
+			AstNode::Function(Box::new(body))
 		},
-		&AstNode::Application(ref a, ref b) =>
-			reduce_application(&**a, &**b, to_fn),
-		&AstNode::Name(..) =>
-			panic!("name node in expression"), 
-		_ => 
-			// variables can't be reduced, just copy them
-			copy_node(node),
+		AstNode::Application(a, b) =>
+			AstNode::Application(Box::new(eta_reduce(a)), Box::new(eta_reduce(b))),
+		_ => node.clone(),
+	}
+}
+
+/// Wraps the term as `\x.f x`, the opposite of an eta reduction.
+pub fn eta_expand(node: &AstNode) -> AstNode {
+	AstNode::Function(Box::new(AstNode::Application(
+		Box::new(increment_free(node, 1, 0)),
+		Box::new(AstNode::BoundVariable(0)))))
+}
+
+/// Finds the first place, in leftmost outermost order, where the
+/// terms aren't alpha-equivalent. Returns the differing subterms
+/// with the number of binders they are under.
+pub fn first_difference<'a>(a: &'a AstNode, b: &'a AstNode) -> Option<(u32, &'a AstNode, &'a AstNode)> {
+	let (a, b) = (a.unspanned(), b.unspanned());
+	if alpha_equivalent(a, b) {
+		return None;
+	}
+	match (a, b) {
+		(AstNode::Function(x), AstNode::Function(y)) =>
+			first_difference(x, y).map(|(depth, x, y)| (depth + 1, x, y)),
+		(AstNode::Application(a1, b1), AstNode::Application(a2, b2)) =>
+			first_difference(a1, a2).or_else(|| first_difference(b1, b2)),
+		_ => Some((0, a, b)),
+	}
+}
+
+/// Checks if the terms are the same up to the names of bound
+/// variables, which with de Bruijn indices means they are equal.
+pub fn alpha_equivalent(a: &AstNode, b: &AstNode) -> bool {
+	match (a.unspanned(), b.unspanned()) {
+		(&AstNode::FreeVariable(x), &AstNode::FreeVariable(y)) => x == y,
+		(&AstNode::BoundVariable(x), &AstNode::BoundVariable(y)) => x == y,
+		(AstNode::Function(x), AstNode::Function(y)) => alpha_equivalent(x, y),
+		(AstNode::Application(a1, b1), AstNode::Application(a2, b2)) =>
+			alpha_equivalent(a1, a2) && alpha_equivalent(b1, b2),
+		(AstNode::Name(x), AstNode::Name(y)) => x == y,
+		(&AstNode::Integer(x), &AstNode::Integer(y)) => x == y,
+		(&AstNode::Builtin(x), &AstNode::Builtin(y)) => x == y,
+		(AstNode::Foreign(x), AstNode::Foreign(y)) => x.name == y.name,
+		_ => false,
 	}
 }
 
-pub fn beta_reduce(node: &AstNode) -> AstNode {
-	reduce_node(node, false)
+/// Reasons for the reducer to stop before reaching normal form.
+pub enum Interrupt {
+	/// The primitive the reducer was told to stop at got applied
+	/// to something, so the result depends on what it stands for.
+	Blocked,
+	/// A foreign function reported an error.
+	Failed(String),
+	/// The reducer did as many steps as it was allowed to.
+	OutOfFuel,
+}
+
+/// What to do with eta redexes once the term is in beta normal form.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Eta {
+	/// Leave them as they are.
+	Off,
+	/// Contract them, so that `\x.f x` becomes `f`.
+	Reduce,
+	/// Expand the term to eta-long form, if it has a simple type.
+	Long,
+}
+
+impl Eta {
+	pub fn from_name(name: &str) -> Option<Eta> {
+		match name {
+			"off" => Some(Eta::Off),
+			"reduce" => Some(Eta::Reduce),
+			"long" => Some(Eta::Long),
+			_ => None,
+		}
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Eta::Off => "off",
+			Eta::Reduce => "reduce",
+			Eta::Long => "long",
+		}
+	}
+}
+
+/// Result of reducing an application once its function is known.
+enum Step {
+	Done(AstNode),
+	/// The application was contracted to a term that still
+	/// needs reducing.
+	Again(AstNode),
+}
+
+/// Work done by redexes that came from one definition.
+#[derive(Clone, Copy, Default)]
+pub struct Cost {
+	pub steps: usize,
+	pub substitutions: usize,
+}
+
+/// Work done by a reducer.
+#[derive(Default)]
+pub struct Statistics {
+	/// Number of beta reductions and calls to native or foreign
+	/// functions.
+	pub steps: usize,
+	/// Number of variables replaced by arguments.
+	pub substitutions: usize,
+	/// Size of the largest term that was reduced or that a redex was
+	/// contracted to, counting variables, functions and applications.
+	/// Only measured when profiling.
+	pub peak_size: usize,
+	pub time: Duration,
+	/// Work by the definition the function of each redex came from,
+	/// with `None` for functions written in the term itself. Only
+	/// filled when profiling.
+	pub origins: HashMap<Option<Rc<str>>, Cost>,
+}
+
+pub struct Reducer {
+	blocking_primitive: Option<Primitive>,
+	statistics: Statistics,
+	fuel: Option<usize>,
+	eta: Eta,
+	profiling: bool,
+}
+
+impl Default for Reducer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Reducer {
+	pub fn new() -> Reducer {
+		Reducer {
+			blocking_primitive: None,
+			statistics: Statistics::default(),
+			fuel: None,
+			eta: Eta::Off,
+			profiling: false,
+		}
+	}
+	
+	/// Makes the reducer give up after the given number of steps,
+	/// for terms that might not have a normal form.
+	pub fn set_fuel(&mut self, fuel: usize) {
+		self.fuel = Some(fuel);
+	}
+	
+	/// Makes `beta_reduce` also deal with eta redexes. Terms without
+	/// a simple type are left in beta normal form with `Eta::Long`.
+	pub fn set_eta(&mut self, eta: Eta) {
+		self.eta = eta;
+	}
+	
+	/// Makes the reducer measure term sizes and attribute its work
+	/// to the origins of functions, see `AstNode::Origin`. Origins
+	/// are removed from the result.
+	pub fn set_profiling(&mut self, profiling: bool) {
+		self.profiling = profiling;
+	}
+	
+	/// Number of steps done so far, see `Statistics::steps`.
+	pub fn steps(&self) -> usize {
+		self.statistics.steps
+	}
+	
+	pub fn statistics(&self) -> &Statistics {
+		&self.statistics
+	}
+	
+	/// Makes the reducer give up when the primitive ends up in head
+	/// position of an application, instead of reducing the arguments,
+	/// which might not terminate.
+	pub fn stop_at(&mut self, primitive: &Primitive) {
+		self.blocking_primitive = Some(primitive.clone());
+	}
+	
+	fn reduce_application(&mut self, left: &AstNode, right: &AstNode)
+		-> Result<Step, Interrupt>
+	{
+		let (origin, left_fn) = match self.reduce_node(left, true)? {
+			AstNode::Origin(name, node) => (Some(name), *node),
+			node => (None, node),
+		};
+		match left_fn {
+			AstNode::Function(body) => {
+				if self.fuel.is_some_and(|fuel| self.statistics.steps >= fuel) {
+					return Err(Interrupt::OutOfFuel);
+				}
+				let mut uses = 0;
+				let result = substitute_walk(&body, 0, right, &mut uses);
+				self.record_step(origin, uses, &result);
+				Ok(Step::Again(result))
+			},
+			AstNode::Name(..) =>
+				panic!("name node in expression"),
+			AstNode::Foreign(ref primitive)
+				if self.blocking_primitive.as_ref().is_some_and(|blocking| blocking.is(primitive)) =>
+				Err(Interrupt::Blocked),
+			AstNode::Integer(num) => {
+				// literal used as a function, fall back to Church numeral
+				let numeral = parser::create_church_numeral(num);
+				self.reduce_application(&numeral, right)
+			},
+			_ => {
+				let left_fn = self.reduce_node(&left_fn, false)?;
+				let mut right = self.reduce_node(right, false)?;
+				if self.profiling {
+					// native and foreign functions don't know about origins
+					right = strip_origins(&right);
+				}
+				if let Some(result) = native::delta_reduce(&left_fn, &right) {
+					self.record_step(primitive_name(&left_fn), 0, &result);
+					return Ok(Step::Done(result));
+				}
+				match foreign::invoke(&left_fn, &right) {
+					Some(Ok(result)) => {
+						self.record_step(primitive_name(&left_fn), 0, &result);
+						Ok(Step::Again(result))
+					},
+					Some(Err(message)) => Err(Interrupt::Failed(message)),
+					None => Ok(Step::Done(AstNode::Application(Box::new(left_fn), Box::new(right)))),
+				}
+			},
+		}
+	}
+	
+	fn record_step(&mut self, origin: Option<Rc<str>>, substitutions: usize, result: &AstNode) {
+		self.statistics.steps += 1;
+		self.statistics.substitutions += substitutions;
+		self.record_size(result);
+		if self.profiling {
+			let cost = self.statistics.origins.entry(origin).or_default();
+			cost.steps += 1;
+			cost.substitutions += substitutions;
+		}
+	}
+	
+	fn record_size(&mut self, node: &AstNode) {
+		if self.profiling {
+			self.statistics.peak_size = self.statistics.peak_size.max(size(node));
+		}
+	}
+	
+	fn reduce_node(&mut self, node: &AstNode, to_fn: bool) -> Result<AstNode, Interrupt> {
+		match node {
+			AstNode::Origin(name, node) => {
+				// keep the origin on the function, where it is
+				// needed once the function is applied
+				match self.reduce_node(node, to_fn)? {
+					result @ AstNode::Function(..) => Ok(AstNode::Origin(name.clone(), Box::new(result))),
+					result => Ok(result),
+				}
+			},
+			AstNode::Function(body) => {
+				if to_fn {
+					// already a function, just return copy
+					Ok(copy_node(node))
+				} else {
+					// reduce recursively
+					Ok(AstNode::Function(Box::new(self.reduce_node(body, false)?)))
+				}
+			},
+			AstNode::Application(a, b) => {
+				let mut next = match self.reduce_application(a, b)? {
+					Step::Done(result) => return Ok(result),
+					Step::Again(next) => next,
+				};
+				// contracted redexes are reduced in a loop rather than
+				// recursively, so that long reductions don't overflow the stack
+				loop {
+					let step = match next {
+						AstNode::Application(ref a, ref b) => self.reduce_application(a, b)?,
+						_ => return self.reduce_node(&next, to_fn),
+					};
+					next = match step {
+						Step::Done(result) => return Ok(result),
+						Step::Again(next) => next,
+					};
+				}
+			},
+			&AstNode::Name(..) =>
+				panic!("name node in expression"), 
+			AstNode::Foreign(primitive) if primitive.arity() == 0 => {
+				match foreign::invoke_constant(primitive) {
+					Ok(result) => {
+						self.record_step(Some(Rc::from(&*primitive.name)), 0, &result);
+						self.reduce_node(&result, to_fn)
+					},
+					Err(message) => Err(Interrupt::Failed(message)),
+				}
+			},
+			_ => 
+				// variables can't be reduced, just copy them
+				Ok(copy_node(node)),
+		}
+	}
+	
+	pub fn beta_reduce(&mut self, node: &AstNode) -> Result<AstNode, Interrupt> {
+		self.record_size(node);
+		let start = Instant::now();
+		let result = self.reduce_node(node, false);
+		self.statistics.time += start.elapsed();
+		let mut node = result?;
+		if self.profiling {
+			node = strip_origins(&node);
+		}
+		Ok(match self.eta {
+			Eta::Off => node,
+			Eta::Reduce => eta_reduce(&node),
+			Eta::Long => types::eta_long(&node).unwrap_or(node),
+		})
+	}
+	
+	/// Reduces the node only until it becomes a function, leaving
+	/// the function body as it is.
+	pub fn reduce_to_function(&mut self, node: &AstNode) -> Result<AstNode, Interrupt> {
+		self.record_size(node);
+		let start = Instant::now();
+		let result = self.reduce_node(node, true);
+		self.statistics.time += start.elapsed();
+		result
+	}
+}
+
+pub fn beta_reduce(node: &AstNode) -> Result<AstNode, String> {
+	match Reducer::new().beta_reduce(node) {
+		Ok(result) => Ok(result),
+		Err(Interrupt::Failed(message)) => Err(message),
+		Err(Interrupt::Blocked) | Err(Interrupt::OutOfFuel) => unreachable!(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use native::Builtin;
+	use parser::{self, Syntax};
+
+	fn term(source: &str) -> AstNode {
+		parser::parse(source, Syntax::Lambda).ok().unwrap()
+	}
+
+	fn profile(node: &AstNode) -> (AstNode, Statistics) {
+		let mut reducer = Reducer::new();
+		reducer.set_profiling(true);
+		let result = reducer.beta_reduce(node).ok().unwrap();
+		(result, reducer.statistics)
+	}
+
+	#[test]
+	fn eta_redexes_are_contracted() {
+		assert!(alpha_equivalent(&eta_reduce(&term("\\x.\\y.f x y")), &term("f")));
+		// x is used in the function, so this is not an eta redex
+		assert!(alpha_equivalent(&eta_reduce(&term("\\x.x x")), &term("\\x.x x")));
+		let expanded = eta_expand(&term("\\x.f x"));
+		assert!(alpha_equivalent(&expanded, &term("\\y.(\\x.f x) y")));
+		assert!(alpha_equivalent(&eta_reduce(&expanded), &term("f")));
+	}
+
+	#[test]
+	fn single_steps_start_with_normal_order() {
+		let steps = single_steps(&term("(\\x.x) ((\\y.y) z)"));
+		assert_eq!(steps.len(), 2);
+		assert!(alpha_equivalent(&steps[0], &term("(\\y.y) z")));
+		assert!(alpha_equivalent(&steps[1], &term("(\\x.x) z")));
+		assert!(contract(&term("x ((\\y.y) z)")).is_none());
+	}
+
+	#[test]
+	fn first_difference_is_located() {
+		let (a, b) = (term("\\x.x a"), term("\\y.y b"));
+		match first_difference(&a, &b) {
+			Some((1, &AstNode::FreeVariable('a'), &AstNode::FreeVariable('b'))) => {},
+			_ => panic!("wrong difference"),
+		}
+		assert!(first_difference(&a, &term("\\y.y a")).is_none());
+	}
+
+	#[test]
+	fn peak_size_includes_the_initial_term() {
+		let node = term("(\\x.x) (\\x.\\y.\\z.x y z)");
+		let (_, statistics) = profile(&node);
+		assert_eq!(statistics.steps, 1);
+		assert_eq!(statistics.peak_size, size(&node));
+	}
+
+	#[test]
+	fn delta_steps_are_counted() {
+		let node = AstNode::Application(
+			Box::new(AstNode::Application(
+				Box::new(AstNode::Builtin(Builtin::Add)),
+				Box::new(AstNode::Integer(2)))),
+			Box::new(AstNode::Integer(3)));
+		let (result, statistics) = profile(&node);
+		match result {
+			AstNode::Integer(5) => {},
+			_ => panic!("2 + 3 wasn't reduced to 5"),
+		}
+		assert_eq!(statistics.steps, 1);
+		assert_eq!(statistics.peak_size, 5);
+		assert_eq!(statistics.origins[&Some(Rc::from("+"))].steps, 1);
+	}
+
+	#[test]
+	fn fuel_limits_steps() {
+		let mut reducer = Reducer::new();
+		reducer.set_fuel(10);
+		match reducer.beta_reduce(&term("(\\x.x x) (\\x.x x)")) {
+			Err(Interrupt::OutOfFuel) => {},
+			_ => panic!("omega didn't run out of fuel"),
+		}
+		assert_eq!(reducer.steps(), 10);
+	}
 }
@@ -1,21 +1,79 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 use AstNode;
-use pretty_print;
+use pretty_string;
 use parser;
+use printer::{self, Pretty, Style};
 use reduction;
+use combinators;
+use foreign::{Foreign, Primitive};
+use native::Builtin;
+use blc;
+use diagnostic::{Diagnostic, Source, Span};
+use dot;
+use format;
+use lint;
+use check::{self, Check};
+use property;
+use types;
+use debugger::Debugger;
+use json::{self, Json};
 
-pub struct Interpreter {
-	named_fns: HashMap<String, AstNode>
+/// What terms are resolved into before evaluation.
+#[derive(Clone, Copy, PartialEq)]
+enum Target {
+	/// Plain lambda terms, as needed for translating them
+	/// to combinators or BLC.
+	Lambda,
+	/// Lambda terms which can call foreign functions.
+	Church,
+	/// Like `Church`, but numbers and arithmetic are native.
+	Native,
 }
 
-fn is_name(ch: char) -> bool {
-	ch >= 'A' && ch <= 'Z' 
+/// How the two terms of `:eq` compare.
+enum Comparison {
+	/// Equivalent up to the named relation, like `beta`.
+	Equivalent(&'static str),
+	/// The normal forms, which differ.
+	Different(AstNode, AstNode),
 }
 
-fn is_digit(ch: char) -> bool {
-	ch >= '0' && ch <= '9'
+pub struct Interpreter {
+	named_fns: HashMap<String, AstNode>,
+	foreign_fns: HashMap<String, Primitive>,
+	syntax: parser::Syntax,
+	quiet: bool,
+	native: bool,
+	json: bool,
+	style: Style,
+	width: Option<usize>,
+	eta: reduction::Eta,
+	/// Names still bound to their prelude definitions.
+	prelude: HashSet<String>,
+	/// Set while a `:debug` session is running, which takes over
+	/// the input until it is quit.
+	debugger: Option<Debugger>,
 }
 
+/// Number of beta reductions the linter spends on finding
+/// the normal form of a definition.
+const LINT_FUEL: usize = 1000;
+
+/// Number of beta reductions a law may take for one case.
+const LAW_FUEL: usize = 10000;
+
+/// Number of beta reductions `:eq` spends on normalising each side.
+const EQ_FUEL: usize = 2000;
+
+/// Number of steps `Interpreter::evaluate` takes before giving up on
+/// a term without a normal form.
+pub const EVALUATE_FUEL: usize = 1000000;
+
 fn split_by_binding(line: &str) -> Option<(String, String)> {
 	let mut last: char = ' ';
 	let mut index: usize = 0;
@@ -43,39 +101,87 @@ fn split_by_binding(line: &str) -> Option<(String, String)> {
 	None
 }
 
-fn print_parse_error(input: &str, err: parser::ParseError) {
-	println!("{}", input);
-	for _ in 0..err.position {
-		print!(" ");
+fn print_parse_errors(source: &Rc<Source>, errors: Vec<parser::ParseError>) {
+	for error in errors {
+		println!("{}", error.diagnostic(source));
 	}
-	println!("^");
-	// + 1 because editors index columns starting from 1
-	println!("Error (column {}): {}", err.position + 1, err.message);
+}
+
+/// Both results, or the errors of either.
+fn both<A, B>(a: Result<A, Diagnostic>, b: Result<B, Diagnostic>) -> Result<(A, B), Vec<Diagnostic>> {
+	match (a, b) {
+		(Ok(a), Ok(b)) => Ok((a, b)),
+		(a, b) => Err(a.err().into_iter().chain(b.err()).collect()),
+	}
+}
+
+fn parse_diagnostics(source: &Rc<Source>, errors: Vec<parser::ParseError>) -> Vec<Diagnostic> {
+	errors.into_iter().map(|error| error.diagnostic(source)).collect()
 }
 
 fn is_valid_name(name: &str) -> bool {
-	if name.is_empty() {
-		println!("name cannot be empty");
-		return false;
+	match parser::check_name(name) {
+		Ok(()) => true,
+		Err(message) => {
+			println!("{}", message);
+			false
+		},
 	}
-	
-	for ch in name.chars() {
-		if !is_digit(ch) && !is_name(ch) {
-			println!("invalid name");
-			return false;
-		} 
+}
+
+fn error_json(message: &str, position: Option<usize>) -> Json {
+	Json::object(vec![
+		("message", Json::string(message)),
+		// + 1 because editors index columns starting from 1
+		("column", position.map_or(Json::Null, |p| Json::Number((p + 1) as f64))),
+	])
+}
+
+/// Error for JSON mode, located if it is in the given source rather
+/// than in a definition.
+fn diagnostic_json(diagnostic: &Diagnostic, source: &Rc<Source>) -> Json {
+	let position = match diagnostic.span {
+		Some(ref span) if Rc::ptr_eq(&span.source, source) => Some(span.start),
+		_ => None,
+	};
+	error_json(&diagnostic.message, position)
+}
+
+fn diagnostics_json(diagnostics: &[Diagnostic], source: &Rc<Source>) -> Vec<Json> {
+	diagnostics.iter().map(|diagnostic| diagnostic_json(diagnostic, source)).collect()
+}
+
+/// Value of a native integer or a Church numeral.
+pub fn numeric_value(node: &AstNode) -> Option<u32> {
+	if let &AstNode::Integer(num) = node {
+		return Some(num);
 	}
-	
-	// we checked earlier that the string is not empty
-	if !is_name(name.chars().nth(0).unwrap()) {
-		println!("invalid name");
-		return false;
+	church_numeral(node)
+}
+
+/// Like `numeric_value`, but unless eta is off the numeral can also
+/// be eta-reduced or eta-expanded, like `\f.f` for 1.
+pub fn numeric_value_eta(node: &AstNode, eta: reduction::Eta) -> Option<u32> {
+	numeric_value(node).or_else(|| {
+		if eta == reduction::Eta::Off {
+			return None;
+		}
+		match reduction::eta_reduce(node) {
+			// 1 is \f.\x.f x, which eta-reduces to \f.f
+			AstNode::Function(ref body) if is_bound(body, 0) => Some(1),
+			reduced => church_numeral(&reduced),
+		}
+	})
+}
+
+fn is_bound(node: &AstNode, index: u32) -> bool {
+	match node {
+		&AstNode::BoundVariable(i) => i == index,
+		_ => false,
 	}
-	
-	true
 }
 
-fn numeric_value(node: &AstNode) -> Option<u32> {
+fn church_numeral(node: &AstNode) -> Option<u32> {
 	let mut result = 0u32;
 	let mut current_node = node;
 	
@@ -102,7 +208,17 @@ fn numeric_value(node: &AstNode) -> Option<u32> {
 	}
 }
 
-fn boolean_value(node: &AstNode) -> Option<bool> {
+/// Like `boolean_value`, but unless eta is off `TRUE` and `FALSE` can
+/// also be eta-expanded.
+pub fn boolean_value_eta(node: &AstNode, eta: reduction::Eta) -> Option<bool> {
+	boolean_value(node).or_else(|| match eta {
+		reduction::Eta::Off => None,
+		_ => boolean_value(&reduction::eta_reduce(node)),
+	})
+}
+
+/// Value of `TRUE` or `FALSE`.
+pub fn boolean_value(node: &AstNode) -> Option<bool> {
 	let mut current_node = node;
 	for _ in 0..2 {
 		match current_node {
@@ -118,93 +234,1561 @@ fn boolean_value(node: &AstNode) -> Option<bool> {
 	
 }
 
+fn church_value_string(node: &AstNode, eta: reduction::Eta) -> String {
+	match numeric_value_eta(node, eta) {
+		Some(0) => "0 / False".to_string(),
+		Some(num) => num.to_string(),
+		None => match boolean_value_eta(node, eta) {
+			Some(true) => "True".to_string(),
+			_ => "None".to_string(),
+		}
+	}
+}
+
+fn print_church_value(node: &AstNode, eta: reduction::Eta) {
+	println!("Church value: {}", church_value_string(node, eta));
+}
+
+/// Tags the functions of the term that aren't tagged yet, which are
+/// the ones that didn't come from other definitions.
+fn tag_origin(node: &AstNode, name: &Rc<str>) -> AstNode {
+	match node {
+		AstNode::Function(body) =>
+			AstNode::Origin(name.clone(), Box::new(AstNode::Function(Box::new(tag_origin(body, name))))),
+		AstNode::Application(a, b) =>
+			AstNode::Application(Box::new(tag_origin(a, name)), Box::new(tag_origin(b, name))),
+		_ => node.clone(),
+	}
+}
+
+/// Milliseconds in the duration, with a fraction.
+fn milliseconds(duration: Duration) -> f64 {
+	duration.as_secs() as f64 * 1000.0 + duration.subsec_nanos() as f64 / 1000000.0
+}
+
+fn split_command(line: &str) -> (&str, &str) {
+	let line = line.trim();
+	match line.find(is_whitespace) {
+		Some(index) => (&line[1..index], &line[index..]),
+		None => (&line[1..], ""),
+	}
+}
+
+fn is_json_path(path: &str) -> bool {
+	Path::new(path).extension().and_then(|e| e.to_str()) == Some("json")
+}
+
+fn is_whitespace(ch: char) -> bool {
+	matches!(ch, ' ' | '\t' | '\r' | '\n')
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Interpreter {
 	pub fn new() -> Interpreter {
 		Interpreter {
 			named_fns: HashMap::new(),
+			foreign_fns: HashMap::new(),
+			syntax: parser::Syntax::Lambda,
+			quiet: false,
+			native: false,
+			json: false,
+			style: Style::Ascii,
+			width: None,
+			eta: reduction::Eta::Off,
+			prelude: HashSet::new(),
+			debugger: None,
 		}
 	}
 	
-	fn replace_named_functions(&self, obj: &AstNode) -> Result<AstNode, ()> {
+	/// Makes the foreign function callable by the given name. Names
+	/// bound with `:=` take precedence over foreign functions.
+	pub fn register(&mut self, name: &str, implementation: Rc<dyn Foreign>) {
+		let primitive = Primitive::new(name, implementation);
+		self.foreign_fns.insert(name.to_string(), primitive);
+	}
+
+	/// Copies the named definitions, with references to other
+	/// definitions left unexpanded.
+	pub fn environment(&self) -> format::Environment {
+		let definitions: BTreeMap<String, AstNode> = self.named_fns.iter()
+			.map(|(name, node)| (name.clone(), node.clone()))
+			.collect();
+		format::Environment { definitions }
+	}
+
+	/// Binds every definition of the environment, replacing
+	/// existing definitions with the same names.
+	pub fn extend(&mut self, env: format::Environment) {
+		for (name, node) in env.definitions {
+			self.bind(name, node);
+		}
+	}
+	
+	/// Binds the name, which from then on doesn't count
+	/// as a prelude definition.
+	fn bind(&mut self, name: String, node: AstNode) {
+		self.prelude.remove(&name);
+		self.named_fns.insert(name, node);
+	}
+
+	fn target(&self) -> Target {
+		if self.native { Target::Native } else { Target::Church }
+	}
+
+	/// Inlines named definitions and foreign functions. Unless the
+	/// target is native, numbers become Church numerals and arithmetic
+	/// operators are replaced by their prelude definitions.
+	/// Errors point at the name that couldn't be replaced, if the
+	/// term has spans.
+	fn replace_named_functions(&self, obj: &AstNode, target: Target) -> Result<AstNode, Diagnostic> {
+		self.replace_names(obj, target, false)
+	}
+
+	/// Like `replace_named_functions`, but with `tag` the functions of
+	/// each inlined definition are tagged with its name for profiling.
+	/// Church numerals are tagged with the number.
+	fn replace_names(&self, obj: &AstNode, target: Target, tag: bool) -> Result<AstNode, Diagnostic> {
+		let native = target == Target::Native;
 		match obj {
 			&AstNode::Application(ref a, ref b) =>
 				Ok(AstNode::Application(
-					Box::new(try!(self.replace_named_functions(&**a))),
-					Box::new(try!(self.replace_named_functions(&**b))))),
+					Box::new(self.replace_names(a, target, tag)?),
+					Box::new(self.replace_names(b, target, tag)?))),
 			&AstNode::BoundVariable(v) =>
 				Ok(AstNode::BoundVariable(v)),
 			&AstNode::FreeVariable(v) =>
 				Ok(AstNode::FreeVariable(v)),
 			&AstNode::Function(ref body) =>
 				Ok(AstNode::Function(
-					Box::new(try!(self.replace_named_functions(&**body))))),
+					Box::new(self.replace_names(body, target, tag)?))),
+			&AstNode::Integer(num) if native =>
+				Ok(AstNode::Integer(num)),
+			&AstNode::Integer(num) if tag =>
+				Ok(tag_origin(&parser::create_church_numeral(num), &Rc::from(num.to_string().as_str()))),
+			&AstNode::Integer(num) =>
+				Ok(parser::create_church_numeral(num)),
+			&AstNode::Builtin(builtin) if native =>
+				Ok(AstNode::Builtin(builtin)),
+			AstNode::Foreign(primitive) =>
+				Ok(AstNode::Foreign(primitive.clone())),
+			&AstNode::Builtin(builtin) =>
+				self.replace_names(
+					&AstNode::Name(builtin.prelude_name().to_string()), target, tag),
 			&AstNode::Name(ref name) => {
-				match self.named_fns.get(&**name) {
-					None => {
-						println!("[Error] unknown function: {}", name);
-						Err( () )
+				if let Some(builtin) = Builtin::from_symbol(name) {
+					return self.replace_names(&AstNode::Builtin(builtin), target, tag);
+				}
+				match (self.named_fns.get(&**name), self.foreign_fns.get(&**name)) {
+					(Some(node), _) => {
+						let node = self.replace_names(node, target, tag).map_err(|diagnostic|
+							diagnostic.note(format!("in the definition of {}", name)))?;
+						if tag {
+							Ok(tag_origin(&node, &Rc::from(name.as_str())))
+						} else {
+							Ok(node)
+						}
 					},
-					Some(ref node) => {
-						Ok(try!(self.replace_named_functions(node)))
+					(None, Some(..)) if target == Target::Lambda => {
+						Err(Diagnostic::error(format!("foreign function {} has no lambda term", name)))
+					},
+					(None, Some(primitive)) => {
+						Ok(AstNode::Foreign(primitive.clone()))
+					},
+					(None, None) => {
+						Err(Diagnostic::error(format!("unknown function: {}", name)))
 					},
 				}
-			}
+			},
+			AstNode::Spanned(span, node) =>
+				self.replace_names(node, target, tag)
+					.map_err(|diagnostic| diagnostic.at(Some(span))),
+			AstNode::Origin(name, node) =>
+				Ok(AstNode::Origin(name.clone(), Box::new(self.replace_names(node, target, tag)?))),
 		}
 	}
 
+	/// Reduces the term with the current settings, reporting
+	/// if it can't be done.
+	fn normal_form(&self, obj: &AstNode) -> Option<AstNode> {
+		let node = match self.replace_named_functions(obj, self.target()) {
+			Ok(node) => node,
+			Err(diagnostic) => {
+				println!("{}", diagnostic);
+				return None;
+			},
+		};
+		let mut reducer = reduction::Reducer::new();
+		reducer.set_eta(self.eta);
+		match reducer.beta_reduce(&node) {
+			Ok(reduced) => Some(reduced),
+			Err(reduction::Interrupt::Failed(message)) => {
+				println!("[Error] {}", message);
+				None
+			},
+			Err(reduction::Interrupt::Blocked) | Err(reduction::Interrupt::OutOfFuel) => unreachable!(),
+		}
+	}
+	
 	fn process_object(&self, obj: AstNode) -> bool {
-		let replaced = self.replace_named_functions(&obj);
-		match replaced {
-			Err(..) => false,
-			Ok(node) => {
-				let reduced = reduction::beta_reduce(&node);
+		let reduced = match self.normal_form(&obj) {
+			Some(reduced) => reduced,
+			None => return false,
+		};
+		match self.eta {
+			reduction::Eta::Off => println!("beta-reduced to:"),
+			reduction::Eta::Reduce => println!("beta-eta-reduced to:"),
+			reduction::Eta::Long if types::has_type(&reduced) =>
+				println!("beta-reduced to eta-long form:"),
+			reduction::Eta::Long => {
+				println!("[Warning] the term has no simple type, so it has no eta-long form");
 				println!("beta-reduced to:");
-				pretty_print(&reduced);
-				println!("");
-				print!("Church value: ");
-				match numeric_value(&reduced) {
-					Some(0) => println!("0 / False"),
-					Some(num) => println!("{}", num),
-					None => match boolean_value(&reduced) {
-						Some(true) => println!("True"),
-						_ => println!("None"),
-					}
+			},
+		}
+		println!("{}", Pretty { node: &reduced, style: self.style, width: self.width });
+		print_church_value(&reduced, self.eta);
+		true
+	}
+	
+	/// Runs an `assert` or an `expect`, which print nothing unless
+	/// they fail.
+	fn run_check(&self, source: &Rc<Source>, check: Check) -> bool {
+		let mut terms = Vec::new();
+		for (start, end) in check::term_ranges(&check, source) {
+			match parser::parse_range(source, start, end, self.syntax) {
+				Ok(node) => terms.push(node),
+				Err(errors) => {
+					print_parse_errors(source, errors);
+					return false;
+				},
+			}
+		}
+		let mut normal_forms = Vec::new();
+		for term in terms.iter() {
+			match self.normal_form(term) {
+				Some(reduced) => normal_forms.push(reduced),
+				None => return false,
+			}
+		}
+		
+		let pretty = |node| Pretty { node, style: self.style, width: None };
+		let failure = match check {
+			Check::Assert(..) => match boolean_value_eta(&normal_forms[0], self.eta) {
+				Some(true) => return true,
+				Some(false) => Diagnostic::error("assertion failed")
+					.note("reduced to FALSE"),
+				None => Diagnostic::error("assertion failed")
+					.note(format!("reduced to {}, which is not TRUE or FALSE", pretty(&normal_forms[0]))),
+			},
+			Check::Expect(..) => {
+				// so that `POW 3 0`, which is \f.f, is 1
+				let left = reduction::eta_reduce(&normal_forms[0]);
+				if reduction::alpha_equivalent(&left, &reduction::eta_reduce(&normal_forms[1])) {
+					return true;
 				}
+				Diagnostic::error("expectation failed")
+					.note(format!("left side reduced to {}", pretty(&normal_forms[0])))
+					.note(format!("right side reduced to {}", pretty(&normal_forms[1])))
+			},
+		};
+		println!("{}", failure.at(terms[0].span()));
+		false
+	}
+
+	/// Binds the standard definitions: Church numeral arithmetic,
+	/// booleans, pairs and lists.
+	pub fn load_prelude(&mut self) {
+		self.eval_line("I := \\x.x");
+		self.eval_line("SUCC := \\nfx.f(nfx)");
+		self.eval_line("ADD := \\mnfx.mf(nfx)");
+		self.eval_line("MUL := \\mnf.m(nf)");
+		self.eval_line("POW := \\be.eb");
+		self.eval_line("PRED := \\nfx.n(\\gh.h(gf))(\\u.x)(\\u.u)");
+		self.eval_line("SUB := \\mn.n PRED m");
+		self.eval_line("TRUE := \\xy.x");
+		self.eval_line("FALSE := \\xy.y");
+		self.eval_line("AND := \\pq.pqp");
+		self.eval_line("OR := \\pq.ppq");
+		self.eval_line("NOT := \\pab.pba");
+		self.eval_line("IF := \\pab.pab");
+		self.eval_line("ZERO := \\n.n(\\x.FALSE)TRUE");
+		self.eval_line("LEQ := \\mn.ZERO (SUB m n)");
+		self.eval_line("EQ := \\mn.AND (LEQ m n) (LEQ n m)");
+		self.eval_line("PAIR := \\xyf.fxy");
+		self.eval_line("FIRST := \\p.p TRUE");
+		self.eval_line("SECOND := \\p.p FALSE");
+		self.eval_line("NIL := \\x.TRUE");
+		self.eval_line("NULL := \\p.p(\\xy.FALSE)");
+	
+		self.eval_line("HEAD := \\p.FIRST p");
+		self.eval_line("TAIL := \\p.SECOND p");
+		self.eval_line("CONS := PAIR");
+		self.eval_line("REC := \\f.ff");
+		self.eval_line("REDUCE := REC (\\rfp.IF (NULL (TAIL p)) (HEAD p) (r r f (PAIR (f (HEAD p) (HEAD (TAIL p))) (TAIL (TAIL p)))))");
+		
+		self.prelude = self.named_fns.keys().cloned().collect();
+	}
+
+	/// Stops reporting bound definitions, so that loading scripts
+	/// doesn't interfere with the program's own output.
+	pub fn set_quiet(&mut self, quiet: bool) {
+		self.quiet = quiet;
+	}
+
+	/// Makes every evaluated line print a single JSON object on one
+	/// line instead of free-form text. The object has `input`, `ok`
+	/// and `kind` (`expression`, `definition` or `command`) members.
+	/// Failed lines have `errors`, each with `message` and `column`,
+	/// which is null if the error isn't about a particular place in
+	/// the input, and the first of them as `error`. Definitions have
+	/// `name`. Expressions have `normal_form` as text and `term` in
+	/// the schema of `format::to_json`, `number` and `boolean` with the
+	/// decoded value or null, the number of beta reductions `steps`,
+	/// and `time_ms`. The only commands are `:eq` and `:eq-eta`, which
+	/// have `equivalent` and either `relation` or the `left` and
+	/// `right` normal forms, and `:check`, which has the `number` and
+	/// `boolean` that both evaluations agree on.
+	pub fn set_json(&mut self, json: bool) {
+		self.json = json;
+	}
+
+	/// Parses the expression in the current syntax and replaces
+	/// named definitions in it, reporting any errors.
+	pub fn parse_expression(&self, source: &str) -> Option<AstNode> {
+		self.parse_expression_with(source, self.target())
+	}
+
+	/// Replaces named definitions in the term, for example one built
+	/// with `builder` or `lambda!`, and reduces it to normal form with
+	/// the current eta setting, reporting any errors. Gives up after
+	/// `EVALUATE_FUEL` steps.
+	pub fn evaluate(&self, term: &AstNode) -> Option<AstNode> {
+		let node = match self.replace_named_functions(term, self.target()) {
+			Ok(node) => node,
+			Err(diagnostic) => {
+				println!("{}", diagnostic);
+				return None;
+			},
+		};
+		let mut reducer = reduction::Reducer::new();
+		reducer.set_fuel(EVALUATE_FUEL);
+		reducer.set_eta(self.eta);
+		match reducer.beta_reduce(&node) {
+			Ok(result) => Some(result),
+			Err(reduction::Interrupt::OutOfFuel) => {
+				println!("[Error] no normal form within {} steps", EVALUATE_FUEL);
+				None
+			},
+			Err(reduction::Interrupt::Failed(message)) => {
+				println!("[Error] {}", message);
+				None
+			},
+			Err(reduction::Interrupt::Blocked) => unreachable!(),
+		}
+	}
+
+	fn parse_expression_with(&self, source: &str, target: Target) -> Option<AstNode> {
+		let source = Source::new(source);
+		match parser::parse_spanned(&source, 0, self.syntax) {
+			Ok(obj) => match self.replace_named_functions(&obj, target) {
+				Ok(node) => Some(node),
+				Err(diagnostic) => {
+					println!("{}", diagnostic);
+					None
+				},
+			},
+			Err(errors) => {
+				print_parse_errors(&source, errors);
+				None
+			},
+		}
+	}
+
+	fn show_combinators(&self, source: &str) -> bool {
+		let node = match self.parse_expression_with(source, Target::Lambda) {
+			Some(node) => node,
+			None => return false,
+		};
+		
+		let ski = combinators::compile(&node, false);
+		let turner = combinators::compile(&node, true);
+		println!("lambda term size: {}", combinators::lambda_size(&node));
+		print!("SKI ({} nodes): ", combinators::term_size(&ski));
+		combinators::pretty_print(&ski);
+		println!();
+		print!("Turner ({} nodes): ", combinators::term_size(&turner));
+		combinators::pretty_print(&turner);
+		println!();
+		true
+	}
+
+	fn eval_combinators(&self, source: &str) -> bool {
+		let node = match self.parse_expression_with(source, Target::Lambda) {
+			Some(node) => node,
+			None => return false,
+		};
+		
+		let term = combinators::compile(&node, true);
+		let evaluation = match combinators::reduce(&term) {
+			Ok(evaluation) => evaluation,
+			Err(..) => {
+				println!("[Error] no normal form found within step limit");
+				return false;
+			},
+		};
+		println!("reduced in {} steps to:", evaluation.steps);
+		combinators::pretty_print(&evaluation.normal_form);
+		println!();
+		print!("Church value: ");
+		match evaluation.church_value() {
+			Ok(combinators::ChurchValue::Number(0)) => println!("0 / False"),
+			Ok(combinators::ChurchValue::Number(num)) => println!("{}", num),
+			Ok(combinators::ChurchValue::True) => println!("True"),
+			_ => println!("None"),
+		}
+		true
+	}
+
+	fn show_blc(&self, source: &str, bytes: bool) -> bool {
+		let node = match self.parse_expression_with(source, Target::Lambda) {
+			Some(node) => node,
+			None => return false,
+		};
+		
+		let mut bits = Vec::new();
+		if let Err(message) = blc::encode(&node, &mut bits) {
+			println!("[Error] {}", message);
+			return false;
+		}
+		
+		println!("{} bits ({} bytes)", bits.len(), bits.len().div_ceil(8));
+		if bytes {
+			let hex: Vec<String> = blc::to_bytes(&bits).iter()
+				.map(|byte| format!("{:02x}", byte))
+				.collect();
+			println!("{}", hex.join(" "));
+		} else {
+			println!("{}", blc::to_bit_string(&bits));
+		}
+		true
+	}
+
+	/// Prints the term as parsed, before named definitions are
+	/// replaced, in one of the interchange formats.
+	fn show_term(&self, source: &str, sexp: bool) -> bool {
+		let node = match parser::parse(source, self.syntax) {
+			Ok(node) => node,
+			Err(e) => {
+				println!("{}", e.diagnostic(&Source::new(source)));
+				return false;
+			},
+		};
+		
+		let result = if sexp {
+			format::to_sexp(&node)
+		} else {
+			format::to_json(&node).map(|json| json.to_string())
+		};
+		match result {
+			Ok(text) => {
+				println!("{}", text);
+				true
+			},
+			Err(message) => {
+				println!("[Error] {}", message);
+				false
+			},
+		}
+	}
+
+	/// Writes the named definitions to the file, as JSON if its
+	/// extension is `json` and as S-expressions otherwise.
+	fn export_environment(&self, path: &str) -> bool {
+		let env = self.environment();
+		let result = if is_json_path(path) {
+			format::environment_to_json(&env).map(|json| format!("{}\n", json))
+		} else {
+			format::environment_to_sexp(&env)
+		};
+		let text = match result {
+			Ok(text) => text,
+			Err(message) => {
+				println!("[Error] {}", message);
+				return false;
+			},
+		};
+		
+		match File::create(path).and_then(|mut f| f.write_all(text.as_bytes())) {
+			Ok(..) => {
+				println!("exported {} definitions to {}", env.definitions.len(), path);
+				true
+			},
+			Err(e) => {
+				println!("[Error] failed to write {}: {}", path, e);
+				false
+			},
+		}
+	}
+
+	fn import_environment(&mut self, path: &str) -> bool {
+		let mut contents = String::new();
+		if let Err(e) = File::open(path).and_then(|mut f| f.read_to_string(&mut contents)) {
+			println!("[Error] failed to read {}: {}", path, e);
+			return false;
+		}
+		
+		let result = if is_json_path(path) {
+			json::parse(&contents).and_then(|json| format::environment_from_json(&json))
+		} else {
+			format::environment_from_sexp(&contents)
+		};
+		match result {
+			Ok(env) => {
+				if !self.quiet {
+					println!("imported {} definitions from {}", env.definitions.len(), path);
+				}
+				self.extend(env);
 				true
+			},
+			Err(message) => {
+				println!("[Error] {}: {}", path, message);
+				false
+			},
+		}
+	}
+
+	/// Prints a Graphviz graph of the term, or of all the ways
+	/// it can be reduced.
+	fn show_dot(&self, source: &str, reductions: bool) -> bool {
+		let node = match self.parse_expression_with(source, Target::Lambda) {
+			Some(node) => node,
+			None => return false,
+		};
+		
+		if reductions {
+			print!("{}", dot::reduction_graph(&node));
+		} else {
+			print!("{}", dot::term_graph(&node));
+		}
+		true
+	}
+
+	/// Normalises the side of `:eq`.
+	fn normalise_side(&self, node: &AstNode, side: &str) -> Result<AstNode, Diagnostic> {
+		let mut reducer = reduction::Reducer::new();
+		reducer.set_fuel(EQ_FUEL);
+		match reducer.beta_reduce(node) {
+			Ok(node) => Ok(node),
+			Err(reduction::Interrupt::OutOfFuel) => Err(Diagnostic::error(
+				format!("no normal form of the {} side within {} steps", side, EQ_FUEL))),
+			Err(reduction::Interrupt::Failed(message)) => Err(Diagnostic::error(message)),
+			Err(reduction::Interrupt::Blocked) => unreachable!(),
+		}
+	}
+	
+	/// Reduces the term and prints how much work it took, with the
+	/// steps attributed to the definitions whose functions were
+	/// applied, most expensive first.
+	fn profile(&self, source: &str) -> bool {
+		let source = Source::new(source);
+		let obj = match parser::parse_spanned(&source, 0, self.syntax) {
+			Ok(obj) => obj,
+			Err(errors) => {
+				print_parse_errors(&source, errors);
+				return false;
+			},
+		};
+		let node = match self.replace_names(&obj, self.target(), true) {
+			Ok(node) => node,
+			Err(diagnostic) => {
+				println!("{}", diagnostic);
+				return false;
+			},
+		};
+		let mut reducer = reduction::Reducer::new();
+		reducer.set_eta(self.eta);
+		reducer.set_profiling(true);
+		let reduced = match reducer.beta_reduce(&node) {
+			Ok(reduced) => reduced,
+			Err(reduction::Interrupt::Failed(message)) => {
+				println!("[Error] {}", message);
+				return false;
+			},
+			Err(reduction::Interrupt::Blocked) | Err(reduction::Interrupt::OutOfFuel) => unreachable!(),
+		};
+		println!("{}", Pretty { node: &reduced, style: self.style, width: self.width });
+		print_church_value(&reduced, self.eta);
+		
+		let statistics = reducer.statistics();
+		println!("{} steps, {} substitutions, peak size {}, {:.1} ms",
+			statistics.steps, statistics.substitutions, statistics.peak_size,
+			milliseconds(statistics.time));
+		let mut costs: Vec<_> = statistics.origins.iter().collect();
+		costs.sort_by(|a, b| (b.1.steps, b.1.substitutions).cmp(&(a.1.steps, a.1.substitutions))
+			.then_with(|| a.0.cmp(b.0)));
+		if !costs.is_empty() {
+			println!("{:>10} {:>7} {:>14}  definition", "steps", "share", "substitutions");
+		}
+		for (origin, cost) in costs {
+			let share = cost.steps as f64 * 100.0 / statistics.steps as f64;
+			let name = match origin {
+				Some(name) => &**name,
+				// functions written in the profiled term
+				&None => "(input)",
+			};
+			println!("{:>10} {:>6.1}% {:>14}  {}", cost.steps, share, cost.substitutions, name);
+		}
+		true
+	}
+	
+	/// Starts a `:debug` session for the term, after which lines are
+	/// debugger commands until `quit`.
+	fn start_debugging(&mut self, source: &str) -> bool {
+		let source = Source::new(source);
+		let obj = match parser::parse_spanned(&source, 0, self.syntax) {
+			Ok(obj) => obj,
+			Err(errors) => {
+				print_parse_errors(&source, errors);
+				return false;
+			},
+		};
+		let node = match self.replace_names(&obj, Target::Lambda, true) {
+			Ok(node) => node,
+			Err(diagnostic) => {
+				println!("{}", diagnostic);
+				return false;
+			},
+		};
+		let debugger = Debugger::new(node, self.style, self.width);
+		println!("debugging, commands are step, next, break NAME, back, where, and quit");
+		debugger.show();
+		self.debugger = Some(debugger);
+		true
+	}
+	
+	fn debug_command(&mut self, line: &str) -> bool {
+		if line.trim() == "quit" {
+			self.debugger = None;
+			return true;
+		}
+		match self.debugger {
+			Some(ref mut debugger) => debugger.command(line),
+			None => unreachable!(),
+		}
+	}
+	
+	/// What to prompt the user with, which shows if a `:debug`
+	/// session is running.
+	pub fn prompt(&self) -> &'static str {
+		if self.debugger.is_some() {
+			"debug> "
+		} else {
+			"> "
+		}
+	}
+	
+	/// Compares the two terms of an application, like `MUL 2 3`
+	/// and `6` in `:eq MUL 2 3 6`: first up to names of bound
+	/// variables, then by their normal forms, and with `eta` also
+	/// up to eta-conversion. Terms that differ are shown with the
+	/// first place where they do.
+	fn check_equivalence(&self, source: &str, eta: bool) -> bool {
+		let source = Source::new(source);
+		let (left, right) = match self.compare(&source, 0, eta) {
+			Ok(Comparison::Equivalent(relation)) => {
+				println!("{}-equivalent", relation);
+				return true;
+			},
+			Ok(Comparison::Different(left, right)) => (left, right),
+			Err(diagnostics) => {
+				for diagnostic in diagnostics {
+					println!("{}", diagnostic);
+				}
+				return false;
+			},
+		};
+		
+		let renderer = self.style.renderer();
+		println!("not equivalent");
+		println!("left normal form:  {}", printer::render(&left, renderer));
+		println!("right normal form: {}", printer::render(&right, renderer));
+		if let Some((depth, a, b)) = reduction::first_difference(&left, &right) {
+			println!("first difference:  {} vs {}",
+				printer::render_under(a, depth, renderer),
+				printer::render_under(b, depth, renderer));
+		}
+		true
+	}
+	
+	/// Compares the terms of the application that starts at the given
+	/// position of the source, see `check_equivalence`.
+	fn compare(&self, source: &Rc<Source>, start: usize, eta: bool) -> Result<Comparison, Vec<Diagnostic>> {
+		let node = parser::parse_spanned(source, start, self.syntax)
+			.map_err(|errors| parse_diagnostics(source, errors))?;
+		let (left, right) = match node.unspanned() {
+			AstNode::Application(left, right) => (left, right),
+			_ => return Err(vec![Diagnostic::error("expected two terms to compare")
+				.at(node.span())
+				.help("put the terms in parentheses, like `:eq (MUL 2 3) 6`")]),
+		};
+		
+		let (left, right) = both(self.replace_named_functions(left, Target::Church),
+		                         self.replace_named_functions(right, Target::Church))?;
+		if reduction::alpha_equivalent(&left, &right) {
+			return Ok(Comparison::Equivalent("alpha"));
+		}
+		
+		let (mut left, mut right) = both(self.normalise_side(&left, "left"),
+		                                 self.normalise_side(&right, "right"))?;
+		if reduction::alpha_equivalent(&left, &right) {
+			return Ok(Comparison::Equivalent("beta"));
+		}
+		if eta {
+			left = reduction::eta_reduce(&left);
+			right = reduction::eta_reduce(&right);
+			if reduction::alpha_equivalent(&left, &right) {
+				return Ok(Comparison::Equivalent("beta-eta"));
+			}
+		}
+		Ok(Comparison::Different(left, right))
+	}
+	
+	/// Checks the law on random values, see `property::check`,
+	/// reporting the values it fails on.
+	fn check_law(&self, source: &Rc<Source>) -> bool {
+		let law = match property::parse_law(source) {
+			Ok(law) => law,
+			Err(diagnostic) => {
+				println!("{}", diagnostic);
+				return false;
+			},
+		};
+		let body = match parser::parse_spanned(source, law.body, self.syntax) {
+			Ok(body) => body,
+			Err(errors) => {
+				print_parse_errors(source, errors);
+				return false;
+			},
+		};
+		let node = match self.replace_named_functions(&body, self.target()) {
+			Ok(node) => node,
+			Err(diagnostic) => {
+				println!("{}", diagnostic);
+				return false;
+			},
+		};
+		
+		let result = property::check(&law, |values| {
+			let mut reducer = reduction::Reducer::new();
+			reducer.set_fuel(LAW_FUEL);
+			match reducer.beta_reduce(&property::instantiate(&node, values)) {
+				Ok(ref result) if boolean_value_eta(result, self.eta) == Some(true) => Ok(()),
+				Ok(ref result) if boolean_value_eta(result, self.eta) == Some(false) => Err("reduced to FALSE".to_string()),
+				Ok(result) => Err(format!("reduced to {}, which is not TRUE or FALSE",
+					Pretty { node: &result, style: self.style, width: None })),
+				Err(reduction::Interrupt::OutOfFuel) => Err(format!("no normal form within {} steps", LAW_FUEL)),
+				Err(reduction::Interrupt::Failed(message)) => Err(message),
+				Err(reduction::Interrupt::Blocked) => unreachable!(),
 			}
+		});
+		let text = source.text.trim();
+		match result {
+			Ok(()) => {
+				println!("passed {} cases: {}", property::CASES, text);
+				true
+			},
+			Err(failure) => {
+				let values: Vec<String> = failure.values.iter()
+					.map(|&(name, ref value)| format!("{} = {}", name, value))
+					.collect();
+				println!("{}", Diagnostic::error(format!("law doesn't hold for {}", values.join(", ")))
+					.at(body.span())
+					.note(failure.message)
+					.note(match failure.shrinks {
+						0 => format!("found on case {}", failure.case),
+						1 => format!("found on case {}, then shrunk once", failure.case),
+						shrinks => format!("found on case {}, then shrunk {} times", failure.case, shrinks),
+					}));
+				false
+			},
+		}
+	}
+	
+	fn change_native(&mut self, setting: &str) -> bool {
+		match setting {
+			"" => {
+				let state = if self.native { "on" } else { "off" };
+				println!("native integers: {}", state);
+				true
+			},
+			"on" => {
+				self.native = true;
+				true
+			},
+			"off" => {
+				self.native = false;
+				true
+			},
+			_ => {
+				println!("[Error] expected on or off");
+				false
+			},
 		}
 	}
 
-	pub fn eval_line(&mut self, line: &str) -> bool {
-		if let Some((name, expr)) = split_by_binding(line) {
-			let name: String = name.trim().to_string();
-			if !is_valid_name(&name) {
-				return false
+	/// Evaluates the expression both with native integers and with
+	/// the pure Church encoding, and checks that the results agree.
+	fn check_native(&self, source: &str) -> bool {
+		let source = Source::new(source);
+		let (native, pure) = match self.native_and_pure(&source, 0) {
+			Ok(results) => results,
+			Err(diagnostics) => {
+				for diagnostic in diagnostics {
+					println!("{}", diagnostic);
+				}
+				return false;
+			},
+		};
+		
+		let native_value = church_value_string(&native, self.eta);
+		let pure_value = church_value_string(&pure, self.eta);
+		println!("native: {}", native_value);
+		println!("pure: {}", pure_value);
+		if pure_value == "None" {
+			println!("[Error] result is not a number or a boolean");
+			false
+		} else if native_value == pure_value {
+			println!("results agree");
+			true
+		} else {
+			println!("[Error] results differ");
+			false
+		}
+	}
+
+	/// Normal forms of the expression that starts at the given position
+	/// of the source, with native integers and without.
+	fn native_and_pure(&self, source: &Rc<Source>, start: usize) -> Result<(AstNode, AstNode), Vec<Diagnostic>> {
+		let reduce = |target| {
+			let node = parser::parse_spanned(source, start, self.syntax)
+				.map_err(|errors| parse_diagnostics(source, errors))?;
+			let node = self.replace_named_functions(&node, target)
+				.map_err(|diagnostic| vec![diagnostic])?;
+			reduction::beta_reduce(&node)
+				.map_err(|message| vec![Diagnostic::error(message)])
+		};
+		let native = reduce(Target::Native)?;
+		let pure = reduce(Target::Church)?;
+		Ok((native, pure))
+	}
+
+	fn change_syntax(&mut self, name: &str) -> bool {
+		if name.is_empty() {
+			println!("current syntax: {}", self.syntax.name());
+			return true;
+		}
+		
+		match parser::Syntax::from_name(name) {
+			Some(syntax) => {
+				self.syntax = syntax;
+				true
+			},
+			None => {
+				println!("[Error] unknown syntax: {} \
+				          (expected lambda, cl, iota, jot, or blc)", name);
+				false
+			},
+		}
+	}
+
+	fn change_style(&mut self, name: &str) -> bool {
+		if name.is_empty() {
+			println!("current style: {}", self.style.name());
+			return true;
+		}
+		
+		match Style::from_name(name) {
+			Some(style) => {
+				self.style = style;
+				true
+			},
+			None => {
+				println!("[Error] unknown style: {} \
+				          (expected ascii, unicode, latex, or latex-indices)", name);
+				false
+			},
+		}
+	}
+
+	fn change_eta(&mut self, name: &str) -> bool {
+		if name.is_empty() {
+			println!("eta: {}", self.eta.name());
+			return true;
+		}
+		
+		match reduction::Eta::from_name(name) {
+			Some(eta) => {
+				self.eta = eta;
+				true
+			},
+			None => {
+				println!("[Error] expected off, reduce, or long");
+				false
+			},
+		}
+	}
+	
+	fn change_width(&mut self, setting: &str) -> bool {
+		match setting {
+			"" => {
+				match self.width {
+					Some(width) => println!("line width: {}", width),
+					None => println!("line width: off"),
+				}
+				true
+			},
+			"off" => {
+				self.width = None;
+				true
+			},
+			_ => match setting.parse() {
+				Ok(width) if width > 0 => {
+					self.width = Some(width);
+					true
+				},
+				_ => {
+					println!("[Error] expected a positive width or off");
+					false
+				},
+			},
+		}
+	}
+
+	fn eval_command(&mut self, line: &str) -> bool {
+		let (command, argument) = split_command(line);
+		match command {
+			"ski" => self.show_combinators(argument),
+			"ski-eval" => self.eval_combinators(argument),
+			"syntax" => self.change_syntax(argument.trim()),
+			"style" => self.change_style(argument.trim()),
+			"width" => self.change_width(argument.trim()),
+			"eta" => self.change_eta(argument.trim()),
+			"native" => self.change_native(argument.trim()),
+			"check" => self.check_native(argument),
+			"blc" => self.show_blc(argument, false),
+			"blc8" => self.show_blc(argument, true),
+			"dot" => self.show_dot(argument, false),
+			"dot-reductions" => self.show_dot(argument, true),
+			"eq" => self.check_equivalence(argument, false),
+			"eq-eta" => self.check_equivalence(argument, true),
+			"json" => self.show_term(argument, false),
+			"sexp" => self.show_term(argument, true),
+			"export" => self.export_environment(argument.trim()),
+			"import" => self.import_environment(argument.trim()),
+			"lint" => self.lint_command(argument),
+			"profile" => self.profile(argument),
+			"debug" => self.start_debugging(argument),
+			_ => {
+				println!("[Error] unknown command: {}", command);
+				false
+			},
+		}
+	}
+
+	fn define_json(&mut self, source: &Rc<Source>) -> Result<Vec<(&'static str, Json)>, Vec<Json>> {
+		match self.define(source, 0) {
+			Ok(name) => Ok(vec![("name", Json::String(name))]),
+			Err(diagnostics) => Err(diagnostics_json(&diagnostics, source)),
+		}
+	}
+
+	fn evaluate_json(&self, source: &Rc<Source>) -> Result<Vec<(&'static str, Json)>, Vec<Json>> {
+		let obj = parser::parse_spanned(source, 0, self.syntax)
+			.map_err(|errors| diagnostics_json(&parse_diagnostics(source, errors), source))?;
+		let node = self.replace_named_functions(&obj, self.target())
+			.map_err(|diagnostic| vec![diagnostic_json(&diagnostic, source)])?;
+		
+		let start = Instant::now();
+		let mut reducer = reduction::Reducer::new();
+		reducer.set_eta(self.eta);
+		let reduced = match reducer.beta_reduce(&node) {
+			Ok(reduced) => reduced,
+			Err(reduction::Interrupt::Failed(message)) => return Err(vec![error_json(&message, None)]),
+			Err(reduction::Interrupt::Blocked) | Err(reduction::Interrupt::OutOfFuel) => unreachable!(),
+		};
+		let elapsed = start.elapsed();
+		
+		Ok(vec![
+			("normal_form", Json::String(pretty_string(&reduced))),
+			// foreign functions left in the result can't be written out
+			("term", format::to_json(&reduced).unwrap_or(Json::Null)),
+			("number", numeric_value_eta(&reduced, self.eta).map_or(Json::Null, |n| Json::Number(n as f64))),
+			("boolean", boolean_value_eta(&reduced, self.eta).map_or(Json::Null, Json::Bool)),
+			("steps", Json::Number(reducer.steps() as f64)),
+			("time_ms", Json::Number(milliseconds(elapsed))),
+		])
+	}
+
+	/// `:eq` and `:eq-eta` in JSON mode, with the argument starting at
+	/// the given position of the line.
+	fn compare_json(&self, source: &Rc<Source>, start: usize, eta: bool) -> Result<Vec<(&'static str, Json)>, Vec<Json>> {
+		match self.compare(source, start, eta) {
+			Ok(Comparison::Equivalent(relation)) => Ok(vec![
+				("equivalent", Json::Bool(true)),
+				("relation", Json::string(relation)),
+			]),
+			Ok(Comparison::Different(left, right)) => Ok(vec![
+				("equivalent", Json::Bool(false)),
+				("left", Json::String(pretty_string(&left))),
+				("right", Json::String(pretty_string(&right))),
+			]),
+			Err(diagnostics) => Err(diagnostics_json(&diagnostics, source)),
+		}
+	}
+
+	/// `:check` in JSON mode, with the argument starting at the given
+	/// position of the line.
+	fn check_native_json(&self, source: &Rc<Source>, start: usize) -> Result<Vec<(&'static str, Json)>, Vec<Json>> {
+		let (native, pure) = self.native_and_pure(source, start)
+			.map_err(|diagnostics| diagnostics_json(&diagnostics, source))?;
+		let native_value = church_value_string(&native, self.eta);
+		let pure_value = church_value_string(&pure, self.eta);
+		if pure_value == "None" {
+			Err(vec![error_json("result is not a number or a boolean", None)])
+		} else if native_value != pure_value {
+			let message = format!("results differ: {} native, {} pure", native_value, pure_value);
+			Err(vec![error_json(&message, None)])
+		} else {
+			Ok(vec![
+				("number", numeric_value_eta(&pure, self.eta).map_or(Json::Null, |n| Json::Number(n as f64))),
+				("boolean", boolean_value_eta(&pure, self.eta).map_or(Json::Null, Json::Bool)),
+			])
+		}
+	}
+
+	fn command_json(&self, source: &Rc<Source>) -> Result<Vec<(&'static str, Json)>, Vec<Json>> {
+		let line = &*source.text;
+		let (command, argument) = split_command(line);
+		let start = line.trim_end().chars().count() - argument.chars().count();
+		match command {
+			"eq" => self.compare_json(source, start, false),
+			"eq-eta" => self.compare_json(source, start, true),
+			"check" => self.check_native_json(source, start),
+			_ => {
+				let column = line.chars().take_while(|ch| ch.is_whitespace()).count();
+				let message = format!(":{} is not supported in JSON mode", command);
+				Err(vec![error_json(&message, Some(column))])
+			},
+		}
+	}
+
+	fn eval_line_json(&mut self, source: &Rc<Source>) -> bool {
+		let line = &*source.text;
+		let (kind, result) = if line.trim().starts_with(':') {
+			("command", self.command_json(source))
+		} else if split_by_binding(line).is_some() {
+			("definition", self.define_json(source))
+		} else {
+			("expression", self.evaluate_json(source))
+		};
+		
+		let success = result.is_ok();
+		let mut members = vec![
+			("input", Json::string(line)),
+			("ok", Json::Bool(success)),
+			("kind", Json::string(kind)),
+		];
+		match result {
+			Ok(fields) => members.extend(fields),
+			Err(errors) => {
+				members.push(("error", errors[0].clone()));
+				members.push(("errors", Json::Array(errors)));
+			},
+		}
+		println!("{}", Json::object(members));
+		success
+	}
+
+	pub fn is_defined(&self, name: &str) -> bool {
+		self.named_fns.contains_key(name)
+	}
+	
+	/// Binds the definition `NAME := TERM` that starts at the given
+	/// position of the source, and returns the name.
+	pub fn define(&mut self, source: &Rc<Source>, start: usize) -> Result<String, Vec<Diagnostic>> {
+		let text: String = source.text.chars().skip(start).collect();
+		let (name, expr) = match split_by_binding(&text) {
+			Some(parts) => parts,
+			None => {
+				let span = Span::new(source, start, source.text.chars().count());
+				return Err(vec![Diagnostic::error("expected a definition")
+					.at(Some(&span))
+					.help("write it like `NAME := TERM`")]);
+			},
+		};
+		let indent = name.chars().take_while(|ch| ch.is_whitespace()).count();
+		let name = name.trim();
+		if let Err(message) = parser::check_name(name) {
+			let span = Span::new(source, start + indent, start + indent + name.chars().count());
+			return Err(vec![Diagnostic::error(message).at(Some(&span))]);
+		}
+		
+		let offset = start + text.chars().count() - expr.chars().count();
+		match parser::parse_spanned(source, offset, self.syntax) {
+			Ok(node) => {
+				self.bind(name.to_string(), node);
+				Ok(name.to_string())
+			},
+			Err(errors) => Err(errors.into_iter().map(|error| error.diagnostic(source)).collect()),
+		}
+	}
+	
+	/// Binds the definitions of the script and ignores everything
+	/// else in it. Problems are returned rather than printed.
+	pub fn load_definitions(&mut self, file: &str, contents: &str) -> Vec<Diagnostic> {
+		let mut problems = Vec::new();
+		for statement in parser::split_statements(contents) {
+			let source = Source::in_file(file, statement.line, &statement.text);
+			if property::is_law(&source.text) || check::parse_check(&source).is_some() {
+				continue;
 			}
-			
-			match parser::parse_object(&expr) {
-				Ok(obj) => {
-					print!("bound {} to ", name);
-					pretty_print(&obj);
-					println!("");
-					self.named_fns.insert(name, obj);
+			if split_by_binding(&source.text).is_some() {
+				if let Err(diagnostics) = self.define(&source, 0) {
+					problems.extend(diagnostics);
+				}
+			}
+		}
+		problems
+	}
+	
+	/// Parses the term and reduces it with at most `fuel` beta
+	/// reductions. Problems are returned rather than printed.
+	pub fn evaluate_source(&self, source: &str, fuel: usize) -> Result<AstNode, String> {
+		let source = Source::new(source);
+		let node = parser::parse_spanned(&source, 0, self.syntax)
+			.map_err(|errors| errors[0].message.clone())?;
+		let node = self.replace_named_functions(&node, self.target())
+			.map_err(|diagnostic| diagnostic.message)?;
+		let mut reducer = reduction::Reducer::new();
+		reducer.set_fuel(fuel);
+		reducer.set_eta(self.eta);
+		match reducer.beta_reduce(&node) {
+			Ok(node) => Ok(node),
+			Err(reduction::Interrupt::OutOfFuel) => Err(format!("no normal form within {} steps", fuel)),
+			Err(reduction::Interrupt::Failed(message)) => Err(message),
+			Err(reduction::Interrupt::Blocked) => unreachable!(),
+		}
+	}
+	
+	pub fn eval_line(&mut self, line: &str) -> bool {
+		self.eval_source(Source::new(line))
+	}
+	
+	/// Evaluates a line or a statement of a script. Definitions
+	/// keep their spans, so that errors in them can be pointed at
+	/// when they are used.
+	fn eval_source(&mut self, source: Rc<Source>) -> bool {
+		let line = &*source.text;
+		if parser::is_blank(line) {
+			// nothing but comments
+			return true;
+		}
+		if self.json {
+			return self.eval_line_json(&source);
+		}
+		if self.debugger.is_some() {
+			return self.debug_command(line);
+		}
+		
+		if line.trim().starts_with(':') {
+			return self.eval_command(line);
+		}
+		if property::is_law(line) {
+			return self.check_law(&source);
+		}
+		match check::parse_check(&source) {
+			Some(Ok(check)) => return self.run_check(&source, check),
+			Some(Err(diagnostic)) => {
+				println!("{}", diagnostic);
+				return false;
+			},
+			None => {},
+		}
+		
+		if split_by_binding(line).is_some() {
+			match self.define(&source, 0) {
+				Ok(name) => {
+					if !self.quiet {
+						let node = &self.named_fns[&name];
+						println!("bound {} to {}", name, Pretty { node, style: self.style, width: self.width });
+					}
 					true
 				},
-				Err(e) => {
-					print_parse_error(line, e);
+				Err(diagnostics) => {
+					for diagnostic in diagnostics {
+						println!("{}", diagnostic);
+					}
 					false
 				}
 			}
 		} else {
-			match parser::parse_object(line) {
+			match parser::parse_spanned(&source, 0, self.syntax) {
 				Ok(obj) => {
 					self.process_object(obj)
 				},
-				Err(e) => {
-					print_parse_error(line, e);
+				Err(errors) => {
+					print_parse_errors(&source, errors);
 					false
 				}
 			} 
 		}
 	}
+	
+	/// Evaluates every statement of the file. Statements continue
+	/// on indented lines, see `parser::split_statements`. The syntax
+	/// is picked by the file extension, falling back to the current one.
+	pub fn load_file(&mut self, path: &str) -> bool {
+		self.with_file(path, |interpreter, contents| interpreter.eval_statements(path, contents))
+	}
+	
+	/// Like `load_file`, for the contents of the file, which doesn't
+	/// need to exist.
+	pub fn load_source(&mut self, path: &str, contents: &str) -> bool {
+		self.with_syntax_of(path, |interpreter| interpreter.eval_statements(path, contents))
+	}
+	
+	fn eval_statements(&mut self, path: &str, contents: &str) -> bool {
+		let mut success = true;
+		for statement in parser::split_statements(contents) {
+			let source = Source::in_file(path, statement.line, &statement.text);
+			success = self.eval_source(source) && success;
+		}
+		success
+	}
+	
+	/// Runs `f` on the contents of the file with the syntax picked by
+	/// the file extension, falling back to the current one.
+	fn with_file<F>(&mut self, path: &str, f: F) -> bool
+		where F: FnOnce(&mut Interpreter, &str) -> bool
+	{
+		let mut contents = String::new();
+		match File::open(path).and_then(|mut f| f.read_to_string(&mut contents)) {
+			Ok(..) => (),
+			Err(e) => {
+				println!("[Error] failed to read {}: {}", path, e);
+				return false;
+			},
+		}
+		self.with_syntax_of(path, |interpreter| f(interpreter, &contents))
+	}
+	
+	/// Runs `f` with the syntax picked by the file extension.
+	fn with_syntax_of<F>(&mut self, path: &str, f: F) -> bool
+		where F: FnOnce(&mut Interpreter) -> bool
+	{
+		let old_syntax = self.syntax;
+		let extension = Path::new(path).extension().and_then(|e| e.to_str());
+		if let Some(syntax) = extension.and_then(parser::Syntax::from_extension) {
+			self.syntax = syntax;
+		}
+		let success = f(self);
+		self.syntax = old_syntax;
+		success
+	}
+	
+	/// Finds the normal form with limited fuel, for terms that
+	/// might not have one.
+	fn bounded_normal_form(&self, node: &AstNode) -> Option<AstNode> {
+		let node = match self.replace_named_functions(node, Target::Church) {
+			Ok(node) => node,
+			Err(..) => return None,
+		};
+		let mut reducer = reduction::Reducer::new();
+		reducer.set_fuel(LINT_FUEL);
+		reducer.beta_reduce(&node).ok()
+	}
+	
+	/// Warns about the named definitions and the expressions: unused
+	/// and shadowing binders, free variables in definitions, definitions
+	/// that nothing refers to, and ones with the same normal form as
+	/// a prelude definition. `MAIN` counts as used.
+	fn lint(&self, names: &[String], expressions: &[AstNode]) -> Vec<Diagnostic> {
+		let mut warnings = Vec::new();
+		let mut references = HashSet::new();
+		for (name, node) in self.named_fns.iter() {
+			let mut names = HashSet::new();
+			lint::collect_references(node, &mut names);
+			// recursion doesn't count
+			names.remove(name);
+			references.extend(names);
+		}
+		for node in expressions.iter() {
+			lint::collect_references(node, &mut references);
+			warnings.push(lint::check_term(node, None));
+		}
+		
+		let mut prelude: Vec<&String> = self.prelude.iter().collect();
+		prelude.sort();
+		let prelude_forms: Vec<(&String, AstNode)> = prelude.into_iter()
+			.filter_map(|name| self.bounded_normal_form(&self.named_fns[name]).map(|form| (name, form)))
+			.collect();
+		
+		for name in names.iter() {
+			let node = &self.named_fns[name];
+			let span = lint::definition_span(node);
+			let mut found = lint::check_term(node, Some(name));
+			if !references.contains(name) && name != "MAIN" {
+				found.push(Diagnostic::warning(format!("{} is never used", name))
+					.at(span.as_ref()));
+			}
+			if let Some(form) = self.bounded_normal_form(node) {
+				let same: Vec<&str> = prelude_forms.iter()
+					.filter(|prelude| reduction::alpha_equivalent(&prelude.1, &form))
+					.map(|prelude| &**prelude.0)
+					.collect();
+				if !same.is_empty() {
+					found.push(Diagnostic::warning(
+						format!("{} has the same normal form as {}", name, same.join(", ")))
+						.at(span.as_ref())
+						.help(format!("use {} instead", same[0])));
+				}
+			}
+			warnings.push(found);
+		}
+		
+		// in source order, as far as it is known: by position within
+		// each term, and terms from files by their line
+		for found in warnings.iter_mut() {
+			found.sort_by_key(|warning| warning.span.as_ref().map(|span| span.start));
+		}
+		warnings.sort_by_key(|found| found.iter()
+			.filter_map(|warning| warning.span.as_ref())
+			.map(|span| (span.source.file.clone(), span.source.first_line))
+			.next());
+		warnings.into_iter().flatten().collect()
+	}
+	
+	/// Lints the definitions bound since the prelude,
+	/// or the given expression.
+	fn lint_command(&self, source: &str) -> bool {
+		let (names, expressions) = if source.trim().is_empty() {
+			let mut names: Vec<String> = self.named_fns.keys()
+				.filter(|name| !self.prelude.contains(*name))
+				.cloned()
+				.collect();
+			names.sort();
+			(names, Vec::new())
+		} else {
+			let source = Source::new(source);
+			match parser::parse_spanned(&source, 0, self.syntax) {
+				Ok(node) => (Vec::new(), vec![node]),
+				Err(errors) => {
+					print_parse_errors(&source, errors);
+					return false;
+				},
+			}
+		};
+		
+		let warnings = self.lint(&names, &expressions);
+		for warning in warnings.iter() {
+			println!("{}", warning);
+		}
+		if warnings.is_empty() {
+			println!("no warnings");
+		}
+		warnings.is_empty()
+	}
+	
+	/// Binds the definitions of the file without evaluating anything,
+	/// and warns about them and the expressions in the file, see `lint`.
+	/// Prints nothing if there is nothing to warn about.
+	pub fn lint_file(&mut self, path: &str) -> bool {
+		self.with_file(path, |interpreter, contents| {
+			let mut success = true;
+			let mut names = Vec::new();
+			let mut expressions = Vec::new();
+			for statement in parser::split_statements(contents) {
+				let source = Source::in_file(path, statement.line, &statement.text);
+				let text = &*source.text;
+				if parser::is_blank(text) || text.trim().starts_with(':') {
+					continue;
+				}
+				
+				// terms of laws and checks are linted like expressions
+				let end = text.chars().count();
+				let parts = if property::is_law(text) {
+					property::parse_law(&source).map(|law| (None, vec![(law.body, end)]))
+				} else if let Some(check) = check::parse_check(&source) {
+					check.map(|check| (None, check::term_ranges(&check, &source)))
+				} else {
+					match split_by_binding(text) {
+						Some((name, expr)) => {
+							let name = name.trim().to_string();
+							if !is_valid_name(&name) {
+								success = false;
+								continue;
+							}
+							Ok((Some(name), vec![(end - expr.chars().count(), end)]))
+						},
+						None => Ok((None, vec![(0, end)])),
+					}
+				};
+				let (name, ranges) = match parts {
+					Ok(parts) => parts,
+					Err(diagnostic) => {
+						println!("{}", diagnostic);
+						success = false;
+						continue;
+					},
+				};
+				
+				for (start, end) in ranges {
+					match (parser::parse_range(&source, start, end, interpreter.syntax), &name) {
+						(Ok(node), Some(name)) => {
+							interpreter.bind(name.clone(), node);
+							if !names.contains(name) {
+								names.push(name.clone());
+							}
+						},
+						(Ok(node), &None) => expressions.push(node),
+						(Err(errors), _) => {
+							print_parse_errors(&source, errors);
+							success = false;
+						},
+					}
+				}
+			}
+			
+			let warnings = interpreter.lint(&names, &expressions);
+			for warning in warnings.iter() {
+				println!("{}", warning);
+			}
+			success && warnings.is_empty()
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use parser::Syntax;
+	use reduction::Eta;
+	use output;
+
+	fn term(source: &str) -> AstNode {
+		parser::parse(source, Syntax::Lambda).ok().unwrap()
+	}
+
+	#[test]
+	fn eta_variants_need_eta() {
+		let identity = term("\\f.f");
+		assert_eq!(numeric_value_eta(&identity, Eta::Off), None);
+		assert_eq!(numeric_value_eta(&identity, Eta::Reduce), Some(1));
+		let expanded = term("\\f.\\x.\\y.x y");
+		assert_eq!(numeric_value_eta(&expanded, Eta::Off), None);
+		assert_eq!(numeric_value_eta(&expanded, Eta::Long), Some(0));
+		let expanded_true = term("\\x.\\y.\\z.x z");
+		assert_eq!(boolean_value_eta(&expanded_true, Eta::Off), None);
+		assert_eq!(boolean_value_eta(&expanded_true, Eta::Reduce), Some(true));
+	}
+
+	#[test]
+	fn evaluate_follows_the_interpreter_settings() {
+		let mut interpreter = Interpreter::new();
+		interpreter.set_quiet(true);
+		interpreter.load_prelude();
+		let expanded = term("\\f.\\x.f x");
+		assert!(interpreter.eval_line(":eta reduce"));
+		let result = interpreter.evaluate(&expanded).unwrap();
+		assert!(reduction::alpha_equivalent(&result, &term("\\f.f")));
+		let omega = term("(\\x.x x) (\\x.x x)");
+		let (result, output) = output::capture(|| interpreter.evaluate(&omega));
+		assert!(result.is_none());
+		assert_eq!(output, format!("[Error] no normal form within {} steps\n", EVALUATE_FUEL));
+	}
+
+	#[test]
+	fn church_values_need_no_eta() {
+		assert_eq!(numeric_value_eta(&term("\\f.\\x.f (f x)"), Eta::Off), Some(2));
+		assert_eq!(numeric_value_eta(&term("\\f.\\x.x"), Eta::Off), Some(0));
+		assert_eq!(boolean_value_eta(&term("\\x.\\y.x"), Eta::Off), Some(true));
+	}
+
+	/// Evaluates the line in JSON mode, returning whether it succeeded
+	/// and the printed object.
+	fn json_line(interpreter: &mut Interpreter, line: &str) -> (bool, Json) {
+		let (success, output) = output::capture(|| interpreter.eval_line(line));
+		(success, json::parse(&output).unwrap())
+	}
+
+	fn member(object: &Json, key: &str) -> String {
+		object.get(key).map_or("missing".to_string(), |value| value.to_string())
+	}
+
+	fn json_interpreter() -> Interpreter {
+		let mut interpreter = Interpreter::new();
+		interpreter.set_quiet(true);
+		interpreter.load_prelude();
+		interpreter.set_json(true);
+		interpreter
+	}
+
+	#[test]
+	fn json_definitions_and_expressions() {
+		let mut interpreter = json_interpreter();
+		let (success, object) = json_line(&mut interpreter, "SIX := MUL 2 3");
+		assert!(success);
+		assert_eq!(member(&object, "kind"), r#""definition""#);
+		assert_eq!(member(&object, "name"), r#""SIX""#);
+		let (success, object) = json_line(&mut interpreter, "SUCC SIX");
+		assert!(success);
+		assert_eq!(member(&object, "input"), r#""SUCC SIX""#);
+		assert_eq!(member(&object, "ok"), "true");
+		assert_eq!(member(&object, "kind"), r#""expression""#);
+		assert_eq!(member(&object, "number"), "7");
+		assert_eq!(member(&object, "boolean"), "null");
+		assert_eq!(member(&object, "error"), "missing");
+	}
+
+	#[test]
+	fn json_commands() {
+		let mut interpreter = json_interpreter();
+		let (success, object) = json_line(&mut interpreter, ":eq MUL 2 3 6");
+		assert!(success);
+		assert_eq!(member(&object, "kind"), r#""command""#);
+		assert_eq!(member(&object, "equivalent"), "true");
+		assert_eq!(member(&object, "relation"), r#""beta""#);
+		let (success, object) = json_line(&mut interpreter, ":eq 1 2");
+		assert!(success);
+		assert_eq!(member(&object, "equivalent"), "false");
+		assert_eq!(member(&object, "left"), r#""\\a.\\b.a b""#);
+		let (success, object) = json_line(&mut interpreter, ":check ADD 2 3");
+		assert!(success);
+		assert_eq!(member(&object, "number"), "5");
+		let (success, object) = json_line(&mut interpreter, "  :lint");
+		assert!(!success);
+		assert_eq!(member(&object, "error"),
+			r#"{"message":":lint is not supported in JSON mode","column":3}"#);
+	}
+
+	#[test]
+	fn json_errors_are_all_reported() {
+		let mut interpreter = json_interpreter();
+		let (success, object) = json_line(&mut interpreter, ":eq FOO BAR");
+		assert!(!success);
+		assert_eq!(member(&object, "errors"), format!("[{},{}]",
+			r#"{"message":"unknown function: FOO","column":5}"#,
+			r#"{"message":"unknown function: BAR","column":9}"#));
+		assert_eq!(member(&object, "error"), r#"{"message":"unknown function: FOO","column":5}"#);
+		let (success, object) = json_line(&mut interpreter, "X := \\. a )");
+		assert!(!success);
+		assert_eq!(member(&object, "kind"), r#""definition""#);
+		assert_eq!(member(&object, "errors"), format!("[{},{}]",
+			r#"{"message":"expected letter","column":7}"#,
+			r#"{"message":"expected end of input","column":11}"#));
+	}
 }
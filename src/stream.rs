@@ -0,0 +1,253 @@
+use std::io;
+use std::io::prelude::*;
+use std::rc::Rc;
+use AstNode;
+use foreign::{Arguments, Foreign, Primitive};
+use parser;
+use reduction;
+use reduction::{Interrupt, Reducer};
+use runtime::{boolean_value, numeric_value};
+
+/// Stands for the part of the input that hasn't been read yet. It is
+/// only ever applied by the program, which stops the reducer, so it
+/// is never called.
+struct UnreadInput;
+
+impl Foreign for UnreadInput {
+	fn arity(&self) -> usize {
+		1
+	}
+
+	fn call(&self, _: &Arguments) -> Result<AstNode, String> {
+		Err("input wasn't read yet".to_string())
+	}
+}
+
+fn boolean(value: bool) -> AstNode {
+	let var = if value { 1 } else { 0 };
+	AstNode::Function(Box::new(AstNode::Function(
+		Box::new(AstNode::BoundVariable(var)))))
+}
+
+/// `\xy.FALSE`, applied to a list it gives `TRUE` only for `NIL`.
+fn null_probe() -> AstNode {
+	AstNode::Function(Box::new(AstNode::Function(Box::new(boolean(false)))))
+}
+
+fn apply(a: &AstNode, b: AstNode) -> AstNode {
+	AstNode::Application(Box::new(reduction::copy_node(a)), Box::new(b))
+}
+
+fn mentions_input(node: &AstNode, unread: &Primitive) -> bool {
+	match node {
+		AstNode::Foreign(primitive) => primitive.is(unread),
+		AstNode::Application(a, b) => mentions_input(a, unread) || mentions_input(b, unread),
+		AstNode::Function(body) => mentions_input(body, unread),
+		_ => false,
+	}
+}
+
+fn replace_input(node: &AstNode, unread: &Primitive, input: &AstNode) -> AstNode {
+	match node {
+		AstNode::Foreign(primitive) if primitive.is(unread) =>
+			reduction::copy_node(input),
+		AstNode::Application(a, b) =>
+			AstNode::Application(
+				Box::new(replace_input(a, unread, input)),
+				Box::new(replace_input(b, unread, input))),
+		AstNode::Function(body) =>
+			AstNode::Function(Box::new(replace_input(body, unread, input))),
+		other => reduction::copy_node(other),
+	}
+}
+
+struct Input<R> {
+	source: R,
+	finished: bool,
+	unread: Primitive,
+}
+
+impl<R: BufRead> Input<R> {
+	fn blocking_reducer(&self) -> Reducer {
+		let mut reducer = Reducer::new();
+		reducer.stop_at(&self.unread);
+		reducer
+	}
+
+	/// Brings the list to the form `\f.f head tail`, unless
+	/// that requires input which wasn't read yet.
+	fn force(&self, list: AstNode) -> AstNode {
+		match self.blocking_reducer().reduce_to_function(&list) {
+			Ok(result) => result,
+			// errors will show up again when the list is observed
+			Err(..) => list,
+		}
+	}
+
+	/// Reads the bytes of input that are available, waiting for at
+	/// least one, and splices them into the list in place of the
+	/// unread input. Returns false if the input has already ended.
+	fn feed(&mut self, list: &mut AstNode) -> Result<bool, String> {
+		if self.finished {
+			return Ok(false);
+		}
+
+		let bytes = match self.source.fill_buf() {
+			Ok(bytes) => bytes.to_vec(),
+			Err(e) => return Err(format!("failed to read input: {}", e)),
+		};
+		self.source.consume(bytes.len());
+
+		// NIL := \x.TRUE
+		let mut chunk = if bytes.is_empty() {
+			self.finished = true;
+			AstNode::Function(Box::new(boolean(true)))
+		} else {
+			AstNode::Foreign(self.unread.clone())
+		};
+		for &byte in bytes.iter().rev() {
+			// CONS := \xyf.fxy
+			chunk = AstNode::Function(Box::new(AstNode::Application(
+				Box::new(AstNode::Application(
+					Box::new(AstNode::BoundVariable(0)),
+					Box::new(parser::create_church_numeral(byte as u32)))),
+				Box::new(chunk))));
+		}
+
+		*list = replace_input(list, &self.unread, &chunk);
+		Ok(true)
+	}
+
+	/// Normalises the list applied to the argument, reading more input
+	/// while the result is stuck on input that wasn't read yet.
+	fn observe<T, F>(&mut self, list: &mut AstNode, argument: &AstNode, decode: F)
+		-> Result<T, String>
+		where F: Fn(&AstNode) -> Option<T>
+	{
+		loop {
+			let node = apply(list, reduction::copy_node(argument));
+			let stuck = match self.blocking_reducer().beta_reduce(&node) {
+				Ok(result) => match decode(&result) {
+					Some(value) => return Ok(value),
+					None => mentions_input(&result, &self.unread),
+				},
+				Err(Interrupt::Blocked) => true,
+				Err(Interrupt::OutOfFuel) => unreachable!(),
+				Err(Interrupt::Failed(message)) => return Err(message),
+			};
+			if !stuck || !self.feed(list)? {
+				return Err("output is not a list of numbers".to_string());
+			}
+		}
+	}
+}
+
+/// Runs the program as a stream transformer on standard input and
+/// output, see `transform`.
+pub fn run(program: &AstNode) -> Result<(), String> {
+	let stdin = io::stdin();
+	let stdout = io::stdout();
+	transform(program, stdin.lock(), &mut stdout.lock())
+}
+
+/// Runs the program as a stream transformer: it is applied to the
+/// input as a list of bytes (Church numerals, built with `CONS` and
+/// `NIL`) and the list it returns is written out byte by byte as soon
+/// as each element is known. Input is read only when the program
+/// needs it, as much of it as is available. Like in Lazy K, a number
+/// of 256 or more also ends the output, and so does the reader of the
+/// output going away.
+pub fn transform<R, W>(program: &AstNode, source: R, output: &mut W) -> Result<(), String>
+	where R: BufRead, W: Write
+{
+	let mut input = Input {
+		source,
+		finished: false,
+		unread: Primitive::new("INPUT", Rc::new(UnreadInput)),
+	};
+
+	let unread = AstNode::Foreign(input.unread.clone());
+	let mut list = input.force(apply(program, unread));
+	loop {
+		let is_nil = input.observe(&mut list, &null_probe(), boolean_value)?;
+		if is_nil {
+			return Ok(());
+		}
+
+		let head = input.observe(&mut list, &boolean(true), numeric_value)?;
+		if head > 255 {
+			return Ok(());
+		}
+		let written = output.write_all(&[head as u8]).and_then(|_| output.flush());
+		match written {
+			Ok(()) => {},
+			// the reader is gone, like `head` after it has seen enough
+			Err(ref e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+			Err(e) => return Err(format!("failed to write output: {}", e)),
+		}
+
+		list = input.force(apply(&list, boolean(false)));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use runtime::Interpreter;
+
+	fn program(source: &str) -> AstNode {
+		let mut interpreter = Interpreter::new();
+		interpreter.set_quiet(true);
+		interpreter.load_prelude();
+		interpreter.parse_expression(source).unwrap()
+	}
+
+	fn run_on(source: &str, input: &[u8]) -> Result<Vec<u8>, String> {
+		let mut output = Vec::new();
+		transform(&program(source), input, &mut output)?;
+		Ok(output)
+	}
+
+	#[test]
+	fn echo() {
+		assert_eq!(run_on("\\x.x", b"hello\nworld").unwrap(), b"hello\nworld".to_vec());
+		assert_eq!(run_on("\\x.x", b"").unwrap(), Vec::<u8>::new());
+	}
+
+	#[test]
+	fn programs_see_bytes() {
+		let first = "\\x.CONS (SUCC (HEAD x)) NIL";
+		assert_eq!(run_on(first, b"abc").unwrap(), b"b".to_vec());
+		// free variables of the program don't stand for the input,
+		// whatever their names
+		let program = AstNode::Application(
+			Box::new(program("\\y.\\x.CONS y NIL")),
+			Box::new(AstNode::FreeVariable('_')));
+		assert!(transform(&program, &b"abc"[..], &mut Vec::new()).is_err());
+	}
+
+	/// Input that has one byte ready, and then blocks.
+	struct OneByte(bool);
+
+	impl Read for OneByte {
+		fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+			assert!(!self.0, "read past the available input");
+			self.0 = true;
+			buffer[0] = b'a';
+			Ok(1)
+		}
+	}
+
+	#[test]
+	fn input_is_read_when_needed() {
+		let mut output = Vec::new();
+		let program = program("\\x.CONS (HEAD x) NIL");
+		transform(&program, io::BufReader::new(OneByte(false)), &mut output).unwrap();
+		assert_eq!(output, b"a".to_vec());
+	}
+
+	#[test]
+	fn large_numbers_end_the_output() {
+		assert_eq!(run_on("\\x.CONS 65 (CONS 256 x)", b"abc").unwrap(), b"A".to_vec());
+	}
+}
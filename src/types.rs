@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use AstNode;
+use reduction::eta_expand;
+
+/// Simple type, built from type variables with arrows.
+#[derive(Clone)]
+enum Type {
+	Variable(u32),
+	Arrow(Box<Type>, Box<Type>),
+}
+
+/// Finds the most general simple types, solving equations between
+/// types as they come up.
+struct Inference {
+	/// Types that variables were solved to.
+	solved: HashMap<u32, Type>,
+	next_variable: u32,
+	/// Types of free variables, which are the same at every use.
+	free: HashMap<char, Type>,
+}
+
+impl Inference {
+	fn fresh(&mut self) -> Type {
+		self.next_variable += 1;
+		Type::Variable(self.next_variable - 1)
+	}
+
+	/// Replaces solved variables in the outermost part of the type.
+	fn resolve(&self, ty: &Type) -> Type {
+		match ty {
+			&Type::Variable(var) => match self.solved.get(&var) {
+				Some(ty) => self.resolve(ty),
+				None => ty.clone(),
+			},
+			_ => ty.clone(),
+		}
+	}
+
+	fn occurs(&self, var: u32, ty: &Type) -> bool {
+		match self.resolve(ty) {
+			Type::Variable(other) => var == other,
+			Type::Arrow(a, b) => self.occurs(var, &a) || self.occurs(var, &b),
+		}
+	}
+
+	fn unify(&mut self, a: &Type, b: &Type) -> Result<(), ()> {
+		match (self.resolve(a), self.resolve(b)) {
+			(Type::Variable(x), Type::Variable(y)) if x == y => Ok(()),
+			(Type::Variable(var), ty) | (ty, Type::Variable(var)) => {
+				// infinite types like in \x.x x can't be solved
+				if self.occurs(var, &ty) {
+					return Err(());
+				}
+				self.solved.insert(var, ty);
+				Ok(())
+			},
+			(Type::Arrow(a1, b1), Type::Arrow(a2, b2)) => {
+				self.unify(&a1, &a2)?;
+				self.unify(&b1, &b2)
+			},
+		}
+	}
+
+	/// `env` holds types of bound variables, innermost last.
+	fn infer(&mut self, node: &AstNode, env: &mut Vec<Type>) -> Result<Type, ()> {
+		match node.unspanned() {
+			&AstNode::BoundVariable(num) => Ok(env[env.len() - num as usize - 1].clone()),
+			&AstNode::FreeVariable(ch) => {
+				if let Some(ty) = self.free.get(&ch) {
+					return Ok(ty.clone());
+				}
+				let ty = self.fresh();
+				self.free.insert(ch, ty.clone());
+				Ok(ty)
+			},
+			AstNode::Function(body) => {
+				let param = self.fresh();
+				env.push(param.clone());
+				let body = self.infer(body, env);
+				env.pop();
+				Ok(Type::Arrow(Box::new(param), Box::new(body?)))
+			},
+			AstNode::Application(f, x) => {
+				let f = self.infer(f, env)?;
+				let x = self.infer(x, env)?;
+				let result = self.fresh();
+				self.unify(&f, &Type::Arrow(Box::new(x), Box::new(result.clone())))?;
+				Ok(result)
+			},
+			// numbers, builtins and foreign functions aren't typed
+			_ => Err(()),
+		}
+	}
+
+	/// Expands the term in beta normal form so that every variable is
+	/// applied to as many arguments as its type takes, and every term
+	/// of an arrow type is a function.
+	fn eta_long(&self, node: &AstNode, ty: &Type, env: &mut Vec<Type>) -> AstNode {
+		if let Type::Arrow(param, result) = self.resolve(ty) {
+			let body = match node {
+				AstNode::Function(body) => (**body).clone(),
+				_ => match eta_expand(node) {
+					AstNode::Function(body) => *body,
+					_ => unreachable!(),
+				},
+			};
+			env.push(*param);
+			let body = self.eta_long(&body, &result, env);
+			env.pop();
+			return AstNode::Function(Box::new(body));
+		}
+
+		// the term is a variable applied to arguments
+		let mut args = Vec::new();
+		let mut head = node;
+		while let AstNode::Application(f, x) = head {
+			args.push(&**x);
+			head = &**f;
+		}
+		let mut head_type = match *head {
+			AstNode::BoundVariable(num) => env[env.len() - num as usize - 1].clone(),
+			AstNode::FreeVariable(ch) => self.free[&ch].clone(),
+			_ => unreachable!(),
+		};
+		let mut result = head.clone();
+		for arg in args.into_iter().rev() {
+			let (param, rest) = match self.resolve(&head_type) {
+				Type::Arrow(param, rest) => (param, rest),
+				Type::Variable(..) => unreachable!(),
+			};
+			let arg = self.eta_long(arg, &param, env);
+			result = AstNode::Application(Box::new(result), Box::new(arg));
+			head_type = *rest;
+		}
+		result
+	}
+}
+
+fn new_inference() -> Inference {
+	Inference {
+		solved: HashMap::new(),
+		next_variable: 0,
+		free: HashMap::new(),
+	}
+}
+
+/// Checks if the term has a simple type.
+pub fn has_type(node: &AstNode) -> bool {
+	new_inference().infer(node, &mut Vec::new()).is_ok()
+}
+
+/// Eta-long form of a term in beta normal form, if the term has
+/// a simple type. It is taken with the most general type, so type
+/// variables count as base types.
+pub fn eta_long(node: &AstNode) -> Option<AstNode> {
+	let mut inference = new_inference();
+	match inference.infer(node, &mut Vec::new()) {
+		Ok(ty) => Some(inference.eta_long(node, &ty, &mut Vec::new())),
+		Err(()) => None,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use parser::{self, Syntax};
+	use reduction::alpha_equivalent;
+
+	fn term(source: &str) -> AstNode {
+		parser::parse(source, Syntax::Lambda).ok().unwrap()
+	}
+
+	fn expands_to(source: &str, expected: &str) -> bool {
+		alpha_equivalent(&eta_long(&term(source)).unwrap(), &term(expected))
+	}
+
+	#[test]
+	fn functions_are_expanded() {
+		assert!(expands_to("\\f.\\g.\\x.f g (g x)", "\\f.\\g.\\x.f (\\y.g y) (g x)"));
+		assert!(expands_to("\\x.\\y.y (x y)", "\\x.\\y.y (x (\\z.y z))"));
+	}
+
+	#[test]
+	fn base_types_stay() {
+		// type variables are base types, so the identity isn't expanded
+		assert!(expands_to("\\x.x", "\\x.x"));
+		assert!(expands_to("\\f.\\x.f (f x)", "\\f.\\x.f (f x)"));
+	}
+
+	#[test]
+	fn untypable_terms() {
+		assert!(!has_type(&term("\\x.x x")));
+		assert!(eta_long(&term("\\x.x x")).is_none());
+		assert!(eta_long(&term("\\f.(\\x.f (x x)) (\\x.f (x x))")).is_none());
+		assert!(has_type(&term("\\x.\\y.x")));
+	}
+}
@@ -0,0 +1,57 @@
+//! Runs `lambda fmt` on scripts in a temporary directory.
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// Writes the script to a file of its own, named after the test.
+fn script(test: &str, contents: &str) -> PathBuf {
+	let directory = env::temp_dir().join(format!("lambda-fmt-{}", std::process::id()));
+	fs::create_dir_all(&directory).unwrap();
+	let path = directory.join(format!("{}.lam", test));
+	File::create(&path).and_then(|mut f| f.write_all(contents.as_bytes())).unwrap();
+	path
+}
+
+fn read(path: &PathBuf) -> String {
+	let mut contents = String::new();
+	File::open(path).and_then(|mut f| f.read_to_string(&mut contents)).unwrap();
+	contents
+}
+
+fn fmt(args: &[&str], path: &PathBuf) -> Output {
+	Command::new(env!("CARGO_BIN_EXE_lambda"))
+		.arg("fmt")
+		.args(args)
+		.arg(path)
+		.output()
+		.unwrap()
+}
+
+#[test]
+fn check_fails_on_unformatted_scripts() {
+	let path = script("unformatted", "ID:=\\x.x\n");
+	let output = fmt(&["--check"], &path);
+	assert!(!output.status.success());
+	assert_eq!(String::from_utf8_lossy(&output.stdout),
+		format!("{} is not formatted\n", path.display()));
+	assert_eq!(read(&path), "ID:=\\x.x\n");
+}
+
+#[test]
+fn check_passes_on_formatted_scripts() {
+	let path = script("formatted", "ID := \\x.x\n");
+	let output = fmt(&["--check"], &path);
+	assert!(output.status.success());
+	assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+}
+
+#[test]
+fn scripts_are_formatted_in_place() {
+	let path = script("in_place", "ID:=\\x.x\n");
+	assert!(fmt(&[], &path).status.success());
+	assert_eq!(read(&path), "ID := \\x.x\n");
+	assert!(fmt(&["--check"], &path).status.success());
+}
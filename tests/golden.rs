@@ -0,0 +1,26 @@
+//! Runs the golden scripts under `tests/`, see `lambda::golden`.
+
+extern crate lambda;
+
+use std::path::{Path, PathBuf};
+
+fn directory(name: &str) -> PathBuf {
+	Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join(name)
+}
+
+#[test]
+fn language() {
+	assert!(lambda::golden::run_tests_in_process(directory("language").to_str().unwrap()));
+}
+
+#[test]
+fn prelude() {
+	assert!(lambda::golden::run_tests_in_process(directory("prelude").to_str().unwrap()));
+}
+
+/// The scripts also pass when run by the interpreter binary.
+#[test]
+fn binary() {
+	let program = Path::new(env!("CARGO_BIN_EXE_lambda"));
+	assert!(lambda::golden::run_tests(program, directory("language").to_str().unwrap()));
+}